@@ -0,0 +1,75 @@
+use super::GlobalArgs;
+use clap::{Args, ValueEnum};
+use rellr::libs::changelog::{Changelog, ChangelogSelection};
+use rellr::libs::error::RellrError;
+use rellr::libs::plan::{Action, Plan};
+use rellr::libs::project_config::ProjectConfig;
+use std::fs::write;
+
+const STDOUT: &str = "-";
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ChangelogFormat {
+    #[default]
+    Markdown,
+    /// The processed releases, with grouped commits
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct ChangelogArgs {
+    /// Only the commits since the last release tag
+    #[arg(short, long, conflicts_with_all = ["version", "range"])]
+    unreleased: bool,
+    /// Only the section of this version
+    #[arg(short, long, conflicts_with = "range")]
+    version: Option<String>,
+    /// Only the releases of a commit range, e.g. v1.0.0..HEAD
+    #[arg(short, long)]
+    range: Option<String>,
+    /// File to write the notes to, `-` for stdout
+    #[arg(short, long, default_value = STDOUT)]
+    output: String,
+    /// Changelog of a package of `changelog.packages`
+    #[arg(short, long)]
+    package: Option<String>,
+    /// Format of the notes
+    #[arg(short, long, value_enum, default_value_t = ChangelogFormat::Markdown)]
+    format: ChangelogFormat,
+    /// Print the version that the unreleased commits bump to instead of the notes
+    #[arg(long, conflicts_with_all = ["unreleased", "version", "range", "format"])]
+    bumped_version: bool,
+}
+
+pub fn cmd(changelog_args: ChangelogArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    let selection = match (changelog_args.unreleased, changelog_args.version, changelog_args.range) {
+        (true, _, _) => ChangelogSelection::Unreleased,
+        (_, Some(version), _) => ChangelogSelection::Version(version),
+        (_, _, Some(range)) => ChangelogSelection::Range(range),
+        _ => ChangelogSelection::All,
+    };
+    let changelog = Changelog::open(&ProjectConfig::get()?)?.with_package(changelog_args.package.as_deref())?;
+    let notes = match (changelog_args.bumped_version, changelog_args.format) {
+        (true, _) => format!("{}\n", changelog.bumped_version()?),
+        (false, ChangelogFormat::Markdown) => changelog.render(&selection)?,
+        (false, ChangelogFormat::Json) => format!("{}\n", changelog.export(&selection)?),
+    };
+
+    if changelog_args.output == STDOUT {
+        print!("{}", notes);
+        return Ok(());
+    }
+
+    if global_args.dry_run {
+        let mut plan = Plan::default();
+        plan.push(Action::Changelog {
+            path: changelog_args.output,
+            section: notes,
+        });
+        plan.print(global_args.plan_format);
+        return Ok(());
+    }
+
+    write(&changelog_args.output, notes)?;
+    Ok(())
+}
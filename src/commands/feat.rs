@@ -1,8 +1,9 @@
-use crate::libs::git::{BranchType, Git};
-use crate::libs::msg::{self, Msg};
-use crate::libs::project_config::ProjectConfig;
+use super::GlobalArgs;
 use clap::Args;
-use std::error::Error;
+use rellr::libs::error::RellrError;
+use rellr::libs::git::BranchType;
+use rellr::libs::msg::{self, Msg};
+use rellr::Release;
 
 #[derive(Debug, Args)]
 pub struct FeatArgs {
@@ -10,10 +11,14 @@ pub struct FeatArgs {
     name: String,
 }
 
-pub fn cmd(feat_args: FeatArgs) -> Result<(), Box<dyn Error>> {
-    let mut project_config = ProjectConfig::get()?;
-    project_config.branch_type = BranchType::Feature;
-    Git::new(&project_config).add_branch(&feat_args.name)?.checkout(Some(&feat_args.name))?;
+pub fn cmd(feat_args: FeatArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    let mut release = Release::load()?.dry_run(global_args.dry_run).stash(global_args.stash);
+    release.start_branch(BranchType::Feature, &feat_args.name)?;
+
+    if let Some(plan) = release.plan() {
+        plan.print(global_args.plan_format);
+        return Ok(());
+    }
 
     Msg::new(msg::FEAT_WAS_CREATED).info();
     Ok(())
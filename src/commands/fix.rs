@@ -1,8 +1,9 @@
-use crate::libs::git::{BranchType, Git};
-use crate::libs::msg::{self, Msg};
-use crate::libs::project_config::ProjectConfig;
+use super::GlobalArgs;
 use clap::Args;
-use std::error::Error;
+use rellr::libs::error::RellrError;
+use rellr::libs::git::BranchType;
+use rellr::libs::msg::{self, Msg};
+use rellr::Release;
 
 #[derive(Debug, Args)]
 pub struct FixArgs {
@@ -10,10 +11,14 @@ pub struct FixArgs {
     name: String,
 }
 
-pub fn cmd(fix_args: FixArgs) -> Result<(), Box<dyn Error>> {
-    let mut project_config = ProjectConfig::get()?;
-    project_config.branch_type = BranchType::Hotfix;
-    Git::new(&project_config).add_branch(&fix_args.name)?.checkout(Some(&fix_args.name))?;
+pub fn cmd(fix_args: FixArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    let mut release = Release::load()?.dry_run(global_args.dry_run).stash(global_args.stash);
+    release.start_branch(BranchType::Hotfix, &fix_args.name)?;
+
+    if let Some(plan) = release.plan() {
+        plan.print(global_args.plan_format);
+        return Ok(());
+    }
 
     Msg::new(msg::FIX_WAS_CREATED).info();
     Ok(())
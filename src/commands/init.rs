@@ -1,6 +1,6 @@
-use crate::libs::{project_config::ProjectConfig, msg::{Msg, self}};
+use super::GlobalArgs;
+use rellr::libs::{config_file::ConfigFormat, error::RellrError, plan::Plan, project_config::ProjectConfig, msg::{Msg, self}};
 use clap::Args;
-use std::error::Error;
 
 #[derive(Debug, Args)]
 pub struct InitArgs {
@@ -8,10 +8,22 @@ pub struct InitArgs {
     pub name: String,
     #[arg(short, long)]
     pub version: Option<String>,
+    #[arg(short, long, default_value_t = ConfigFormat::Json, value_enum)]
+    pub format: ConfigFormat,
 }
 
-pub fn cmd(init_args: InitArgs) -> Result<(), Box<dyn Error>> {
-    let _ = ProjectConfig::new_if_not_exist(init_args)?.save()?;
+pub fn cmd(init_args: InitArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    let mut project_config = ProjectConfig::new_if_not_exist(&init_args.name, init_args.version, init_args.format)?;
+
+    if global_args.dry_run {
+        let (before, after) = project_config.render()?;
+        let mut plan = Plan::default();
+        plan.write_file(project_config.config_file_name(), &before, &after);
+        plan.print(global_args.plan_format);
+        return Ok(());
+    }
+
+    project_config.save()?;
     Msg::new(msg::RELLR_FILE_WAS_CREATED).info();
     Ok(())
 }
@@ -1,5 +1,22 @@
+pub mod changelog;
 pub mod feat;
 pub mod fix;
 pub mod init;
 pub mod next;
 pub mod release;
+
+use clap::Args;
+use rellr::libs::plan::PlanFormat;
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Print the planned actions without changing any file, ref or index
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Output format of the dry-run plan
+    #[arg(long, global = true, value_enum, default_value_t = PlanFormat::Text)]
+    pub plan_format: PlanFormat,
+    /// Stash local changes that would be overwritten when switching branches
+    #[arg(long, global = true)]
+    pub stash: bool,
+}
@@ -1,10 +1,12 @@
-use crate::libs::{
-    git::Git,
+use super::GlobalArgs;
+use clap::Args;
+use rellr::libs::{
+    error::RellrError,
     msg::{self, Msg},
-    project_config::ProjectConfig,
+    plan::PlanFormat,
+    version::UpdateType,
 };
-use clap::{Args, ValueEnum};
-use std::error::Error;
+use rellr::Release;
 
 #[derive(Debug, Args)]
 pub struct NextArgs {
@@ -13,21 +15,31 @@ pub struct NextArgs {
         value_enum
     )]
     update_type: UpdateType,
+    /// Pre-release channel, e.g. alpha, beta or rc
+    #[arg(short, long)]
+    channel: Option<String>,
 }
 
-#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
-pub enum UpdateType {
-    Patch,
-    Minor,
-    Major,
-}
+pub fn cmd(next_args: NextArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    let mut release = Release::load()?
+        .dry_run(global_args.dry_run)
+        .stash(global_args.stash)
+        .channel(next_args.channel);
+    let plan = release.bump(&next_args.update_type)?;
+
+    if !plan.reasons.is_empty() && global_args.plan_format == PlanFormat::Text {
+        Msg::new(&format!("{:?} {}", plan.update_type, msg::VERSION_AUTO)).plain();
+        for reason in &plan.reasons {
+            Msg::new(&format!("  {} {}", reason.commit_id, reason.summary)).plain();
+        }
+    }
 
-pub fn cmd(next_args: NextArgs) -> Result<(), Box<dyn Error>> {
-    let mut project_config = ProjectConfig::get()?;
-    let _ = project_config.up_version(&next_args.update_type)?.save()?;
+    if let Some(plan) = release.plan() {
+        plan.print(global_args.plan_format);
+        return Ok(());
+    }
 
-    Git::new(&project_config).add_or_rename_next_branch()?.checkout_next()?;
-    Msg::new(&format!("{} {}", &msg::NEXT, &project_config.next.unwrap())).info();
+    Msg::new(&format!("{} {}", &msg::NEXT, &plan.next)).info();
 
     Ok(())
 }
@@ -1,14 +1,10 @@
-use crate::libs::changelog::Changelog;
-use crate::libs::git::Git;
-use crate::libs::helpers::check_files_existence;
-use crate::libs::msg::{self, Msg};
-use crate::libs::project_config::{ProjectConfig, PROJECT_CONFIG};
+use super::GlobalArgs;
 use clap::Args;
-use regex::Regex;
-use std::error::Error;
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::path::Path;
+use rellr::libs::error::RellrError;
+use rellr::libs::git::MergeOutcome;
+use rellr::libs::journal::Step;
+use rellr::libs::msg::{self, Msg};
+use rellr::Release;
 
 #[derive(Debug, Args)]
 #[command(args_conflicts_with_subcommands = true)]
@@ -16,75 +12,92 @@ pub struct ReleaseArgs {
     project_folder: Option<String>,
     #[arg(short, long)]
     only_changelog: bool,
+    /// Roll back an interrupted release
+    #[arg(long, conflicts_with_all = ["only_changelog", "resume"])]
+    abort: bool,
+    /// Finish the pending steps of an interrupted release
+    #[arg(long = "continue", conflicts_with = "only_changelog")]
+    resume: bool,
 }
 
-pub fn cmd(release_args: ReleaseArgs) -> Result<(), Box<dyn Error>> {
-    let mut project_config = ProjectConfig::get()?;
-
-    if project_config.next.is_none() {
-        Msg::new(msg::RELEASE_VERSION_NOT_SET).error().exit()
+pub fn cmd(release_args: ReleaseArgs, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    if release_args.abort {
+        let (version, plan) = Release::abort(global_args.dry_run)?;
+        match plan {
+            Some(plan) => plan.print(global_args.plan_format),
+            None => {
+                Msg::new(&format!("{} {}", msg::RELEASE_ABORTED, version)).info();
+            }
+        }
+        return Ok(());
     }
 
-    let paths = project_config.paths();
-    let mut paths: Vec<&str> = paths.iter().map(|s| s.as_str()).collect();
-    let non_existent_files = check_files_existence(paths.clone());
-    if !non_existent_files.is_empty() {
-        Msg::new(msg::RELEASE_FAILED).error();
-        Msg::new(&format!("{}\n{}", msg::FILES_ARE_MISSING, &non_existent_files.join("\n"))).warn().exit()
+    if !release_args.resume && Release::is_interrupted()? {
+        return Err(RellrError::Git(msg::RELEASE_IN_PROGRESS.into()));
     }
 
-    // CHANGELOG.md
-    let output_file_name = update_changelog(&project_config)?;
-    paths.push(&output_file_name);
+    let mut release = match release_args.resume {
+        true => Release::resume()?,
+        false => Release::load()?,
+    }
+    .dry_run(global_args.dry_run)
+    .stash(global_args.stash);
+    release.version()?;
+    release.check_files()?;
 
     if release_args.only_changelog {
-        return Ok(());
+        // CHANGELOG.md
+        release.changelog()?;
+        return print_plan(&release, global_args);
     }
 
-    let mut git = Git::new(&project_config).merge()?;
-    let _ = project_config.next_to_current()?.save();
-
-    let version = &project_config.current;
-    let project_name = &project_config.name;
-
-    // UPDATE VERSION
-    for path in &paths {
-        update_version_in_file(path, &project_name, &version)?;
+    let result = release.transaction(|release| {
+        // CHANGELOG.md
+        if release.is_pending(Step::Changelog) {
+            release.changelog()?;
+        }
+
+        if release.is_pending(Step::Merge) {
+            let outcome = release.merge()?;
+            if release.plan().is_none() {
+                match outcome {
+                    MergeOutcome::FastForward => Msg::new(msg::MERGE_FAST_FORWARD).info(),
+                    MergeOutcome::Normal => Msg::new(msg::MERGE_NORMAL).info(),
+                    MergeOutcome::UpToDate => Msg::new(msg::MERGE_UP_TO_DATE).info(),
+                };
+            }
+        }
+
+        // UPDATE VERSION
+        if release.is_pending(Step::UpdateVersions) {
+            release.update_versions()?;
+        }
+
+        // GIT ADD and COMMIT
+        if release.is_pending(Step::Commit) {
+            release.commit()?;
+        }
+        Ok(())
+    });
+    if result.is_err() && release.plan().is_none() && !release.in_progress() {
+        Msg::new(msg::RELEASE_ROLLED_BACK).warn();
     }
+    result?;
 
-    // GIT ADD and COMMIT
-    paths.push(PROJECT_CONFIG);
-    git.commit(paths)?;
-
-    Msg::new(&format!("{} {}", &msg::RELEASE_COMPLETED_SUCCESSFULLY, &version)).info();
-
-    let _ = project_config.publish();
+    if release.plan().is_none() {
+        Msg::new(&format!("{} {}", &msg::RELEASE_COMPLETED_SUCCESSFULLY, &release.version()?)).info();
+    }
 
-    Ok(())
-}
+    for outcome in release.publish()? {
+        Msg::new(&format!("{} {}", msg::PUBLISHED, outcome.name.as_deref().unwrap_or(&outcome.path))).info();
+    }
 
-fn update_changelog(project_config: &ProjectConfig) -> Result<String, Box<dyn Error>> {
-    let mut changelog = Changelog::new(&project_config);
-    let _ = changelog.build();
-    let output_file_name = changelog.output_file_name();
-    Ok(output_file_name)
+    print_plan(&release, global_args)
 }
 
-fn update_version_in_file(path: &str, project_name: &str, next_version: &str) -> Result<(), Box<dyn Error>> {
-    let file_path = Path::new(path);
-    let mut file = File::open(file_path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let re = Regex::new(format!(r#"(?m)("*name("|\s)(:|=)\s"{}",*\s*("*version("|\s)(:|=)\s))("\d+\.\d+\.\d+")"#, project_name).as_str()).unwrap();
-
-    let new_contents = re.replace(&contents, |caps: &regex::Captures| {
-        let version_match = caps.get(1).unwrap().as_str();
-        format!("{}\"{}\"", version_match, next_version)
-    });
-
-    let mut file = OpenOptions::new().write(true).truncate(true).open(file_path)?;
-
-    file.write_all(new_contents.as_bytes())?;
-
+fn print_plan(release: &Release, global_args: &GlobalArgs) -> Result<(), RellrError> {
+    if let Some(plan) = release.plan() {
+        plan.print(global_args.plan_format);
+    }
     Ok(())
 }
@@ -0,0 +1,11 @@
+//! rellr automates the release process: version bumps, release branches,
+//! changelog generation, tagging and publishing.
+//!
+//! The [`Release`] type exposes each step of the workflow so that it can be
+//! driven from other tools as well as from the `rellr` binary.
+
+pub mod libs;
+pub mod release;
+
+pub use libs::error::RellrError;
+pub use release::Release;
@@ -1,78 +1,555 @@
+use super::error::RellrError;
+use super::git::{Git, GitConfig};
+use super::msg::{self, Msg};
 use super::project_config::ProjectConfig;
+use chrono::DateTime;
 use git_cliff_core::changelog::Changelog as GitCliffChangelog;
 use git_cliff_core::commit::Commit;
 use git_cliff_core::config::Config;
-use git_cliff_core::error::Result;
 use git_cliff_core::release::Release;
 use git_cliff_core::repo::Repository;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
 use std::env;
-use std::fs::File;
-use std::io::Write;
+use std::fmt;
+use std::fs::{metadata, read_to_string, write, File};
 
 pub const BUILTIN_CONFIG: &str = include_str!("../../examples/cliff.toml");
+pub const KEEP_A_CHANGELOG_CONFIG: &str = include_str!("../../examples/keepachangelog.toml");
 pub const DEFAULT_OUTPUT: &str = "CHANGELOG.md";
+pub const REPO_CONFIG: &str = "cliff.toml";
+pub const DEFAULT_PACKAGE_TAG: &str = "{name}@{version}";
+const BUILTIN_CONFIG_NAME: &str = "builtin cliff.toml";
+const KEEP_A_CHANGELOG_CONFIG_NAME: &str = "builtin keepachangelog.toml";
 
-#[allow(dead_code)]
-enum ProcessOutput<'a> {
-    Releases(Vec<Release<'a>>),
+/// How the changelog file is updated on release.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogMode {
+    /// Regenerate the whole file from the history.
+    #[default]
+    Full,
+    /// Insert only the section of the new release below the header, keeping the rest of the file.
+    Incremental,
+}
+
+/// Builtin git-cliff configuration used when the repository has none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogTemplate {
+    #[default]
+    Default,
+    /// Sections and groups of [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+    KeepAChangelog,
+}
+
+/// The `changelog` section of the configuration. The legacy form is a string with the output path.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ChangelogOptionsRepr", into = "ChangelogOptionsRepr")]
+pub struct ChangelogOptions {
+    /// Path of the generated changelog.
+    pub output: Option<String>,
+    /// Path of a git-cliff configuration file.
+    pub config: Option<String>,
+    /// Inline git-cliff configuration, with the same `changelog` and `git` tables as `cliff.toml`.
+    pub cliff: Option<JsonValue>,
+    pub mode: Option<ChangelogMode>,
+    /// Builtin git-cliff configuration used when no other one is found.
+    pub template: Option<ChangelogTemplate>,
+    /// Packages of a monorepo that get their own changelog and tag series.
+    pub packages: Vec<ChangelogPackage>,
+}
+
+/// A package with its own changelog, made of the commits that changed its files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangelogPackage {
+    pub name: String,
+    /// Directory of the package, relative to the repository root.
+    pub path: String,
+    /// Globs of the files that belong to the package. Defaults to every file under `path`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs of the files under `include` that are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Path of the package changelog. Defaults to `CHANGELOG.md` in the package directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Name of the package tags. Supports the `{name}` and `{version}` placeholders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// Matches the changed files of a commit against the globs of a package.
+struct PathFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChangelogTable {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cliff: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<ChangelogMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<ChangelogTemplate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    packages: Vec<ChangelogPackage>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ChangelogOptionsRepr {
+    Output(String),
+    Table(ChangelogTable),
+}
+
+/// Picks the form from the shape of the value, so that errors inside the table keep their location.
+impl<'de> Deserialize<'de> for ChangelogOptionsRepr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReprVisitor;
+
+        impl<'de> Visitor<'de> for ReprVisitor {
+            type Value = ChangelogOptionsRepr;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the path of the changelog or a table")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(ChangelogOptionsRepr::Output(value.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                ChangelogTable::deserialize(MapAccessDeserializer::new(map)).map(ChangelogOptionsRepr::Table)
+            }
+        }
+
+        deserializer.deserialize_any(ReprVisitor)
+    }
+}
+
+impl ChangelogOptions {
+    pub fn output_file_name(&self) -> String {
+        self.output.clone().unwrap_or(DEFAULT_OUTPUT.into())
+    }
+
+    /// Tags of all packages for a version.
+    pub fn package_tags(&self, version: &str) -> Vec<String> {
+        self.packages.iter().map(|package| package.tag_name(version)).collect()
+    }
+}
+
+impl ChangelogPackage {
+    pub fn output_file_name(&self) -> String {
+        self.output.clone().unwrap_or(format!("{}/{}", self.path.trim_end_matches('/'), DEFAULT_OUTPUT))
+    }
+
+    pub fn tag_name(&self, version: &str) -> String {
+        self.git_config().tag_name(&self.name, version)
+    }
+
+    pub fn tag_pattern(&self) -> Result<Regex, RellrError> {
+        self.git_config().tag_pattern(&self.name)
+    }
+
+    fn git_config(&self) -> GitConfig {
+        GitConfig {
+            tag: Some(self.tag.clone().unwrap_or(DEFAULT_PACKAGE_TAG.into())),
+            ..GitConfig::default()
+        }
+    }
+
+    fn path_filter(&self) -> Result<PathFilter, RellrError> {
+        let build = |globs: &[String]| -> Result<GlobSet, RellrError> {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(Glob::new(glob).map_err(|e| RellrError::Config(format!("changelog package {}: {}", self.name, e)))?);
+            }
+            builder.build().map_err(|e| RellrError::Config(format!("changelog package {}: {}", self.name, e)))
+        };
+        let include = match self.include.is_empty() {
+            true => vec![format!("{}/**", self.path.trim_end_matches('/'))],
+            false => self.include.clone(),
+        };
+        Ok(PathFilter {
+            include: build(&include)?,
+            exclude: build(&self.exclude)?,
+        })
+    }
+}
+
+impl PathFilter {
+    fn is_match(&self, paths: &[String]) -> bool {
+        paths.iter().any(|path| self.include.is_match(path) && !self.exclude.is_match(path))
+    }
+}
+
+impl From<ChangelogOptionsRepr> for ChangelogOptions {
+    fn from(repr: ChangelogOptionsRepr) -> Self {
+        match repr {
+            ChangelogOptionsRepr::Output(output) => Self {
+                output: Some(output),
+                ..Self::default()
+            },
+            ChangelogOptionsRepr::Table(table) => Self {
+                output: table.output,
+                config: table.config,
+                cliff: table.cliff,
+                mode: table.mode,
+                template: table.template,
+                packages: table.packages,
+            },
+        }
+    }
+}
+
+impl From<ChangelogOptions> for ChangelogOptionsRepr {
+    fn from(options: ChangelogOptions) -> Self {
+        match options {
+            ChangelogOptions {
+                output: Some(output),
+                config: None,
+                cliff: None,
+                mode: None,
+                template: None,
+                packages,
+            } if packages.is_empty() => ChangelogOptionsRepr::Output(output),
+            options => ChangelogOptionsRepr::Table(ChangelogTable {
+                output: options.output,
+                config: options.config,
+                cliff: options.cliff,
+                mode: options.mode,
+                template: options.template,
+                packages: options.packages,
+            }),
+        }
+    }
+}
+
+/// Part of the history rendered by [`Changelog::render`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangelogSelection {
+    /// The whole changelog, with the release being created, header and footer.
+    All,
+    /// Commits since the last release tag.
+    Unreleased,
+    /// The section of a version; an untagged version is assigned to the unreleased commits.
     Version(String),
+    /// The releases of a commit range such as `v1.0.0..v1.2.0` or `v1.0.0..HEAD`.
+    Range(String),
+}
+
+/// A release of the JSON export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReleaseNotes {
+    /// Tag of the release, `None` for the unreleased commits.
+    pub version: Option<String>,
+    /// Date of the tagged commit, `YYYY-MM-DD` in UTC.
+    pub date: Option<String>,
+    pub commit_id: Option<String>,
+    pub groups: Vec<CommitGroup>,
+}
+
+/// Commits of a release that belong to the same group. Groups are ordered by their sort prefix,
+/// then by their first commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitGroup {
+    pub name: Option<String>,
+    pub commits: Vec<CommitNotes>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitNotes {
+    pub id: String,
+    /// Description of a conventional commit, or the first line of the message.
+    pub message: String,
+    #[serde(rename = "type")]
+    pub commit_type: Option<String>,
+    pub scope: Option<String>,
+    pub breaking: bool,
+}
+
+impl ReleaseNotes {
+    /// Parses and groups the commits of `release` the way git-cliff does before rendering the template.
+    fn new(release: &Release, config: &Config) -> Self {
+        // Groups may be prefixed with an HTML comment that sets their order.
+        let sort_prefix = Regex::new(r"^\s*<!--.*?-->").expect("valid regex");
+        let mut groups: Vec<(String, CommitGroup)> = vec![];
+        for commit in release.commits.iter().flat_map(|commit| match config.git.split_commits.unwrap_or(false) {
+            true => commit
+                .message
+                .lines()
+                .map(|line| Commit {
+                    message: line.to_string(),
+                    ..commit.clone()
+                })
+                .collect(),
+            false => vec![commit.clone()],
+        }) {
+            let Ok(commit) = commit.process(&config.git) else {
+                continue;
+            };
+            let conv = commit.conv.as_ref();
+            let group = commit.group.clone().or(conv.map(|c| c.type_().to_string()));
+            let order = group.as_deref().and_then(|group| sort_prefix.find(group)).map(|m| m.as_str().to_string()).unwrap_or_default();
+            let name = group.map(|group| sort_prefix.replace(&group, "").trim().to_string());
+            let notes = CommitNotes {
+                id: commit.id.clone(),
+                message: conv.map_or(commit.message.lines().next().unwrap_or_default().to_string(), |c| c.description().to_string()),
+                commit_type: conv.map(|c| c.type_().to_string()),
+                scope: commit
+                    .scope
+                    .clone()
+                    .or(conv.and_then(|c| c.scope()).map(|scope| scope.to_string()))
+                    .or(commit.default_scope.clone()),
+                breaking: conv.is_some_and(|c| c.breaking()),
+            };
+            match groups.iter_mut().find(|(_, group)| group.name == name) {
+                Some((_, group)) => group.commits.push(notes),
+                None => groups.push((order, CommitGroup { name, commits: vec![notes] })),
+            }
+        }
+        groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Self {
+            version: release.version.clone(),
+            date: release
+                .version
+                .as_ref()
+                .and_then(|_| DateTime::from_timestamp(release.timestamp, 0))
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+            commit_id: release.commit_id.clone(),
+            groups: groups.into_iter().map(|(_, group)| group).collect(),
+        }
+    }
 }
 
 pub struct Changelog {
     pub project_config: ProjectConfig,
-    pub builtin_config: Result<Config>,
+    /// Package the changelog is scoped to, the whole repository when `None`.
+    pub package: Option<ChangelogPackage>,
+    repository: Repository,
+    git: Git,
 }
 
 impl Changelog {
-    pub fn new(project_config: &ProjectConfig) -> Self {
-        let builtin_config = Self::get_builtin_config();
-
-        Self {
+    /// Opens the repository in the current directory.
+    pub fn open(project_config: &ProjectConfig) -> Result<Self, RellrError> {
+        Ok(Self {
             project_config: project_config.clone(),
-            builtin_config,
-        }
+            package: None,
+            repository: Repository::init(env::current_dir()?)?,
+            git: Git::open(project_config)?,
+        })
     }
 
-    pub fn build(&mut self) -> Result<()> {
+    /// Scopes the changelog to a package of `changelog.packages`.
+    pub fn with_package(mut self, name: Option<&str>) -> Result<Self, RellrError> {
+        self.package = match name {
+            Some(name) => Some(
+                self.options()
+                    .packages
+                    .into_iter()
+                    .find(|package| package.name == name)
+                    .ok_or(RellrError::Config(format!("{} `{}`", msg::UNKNOWN_CHANGELOG_PACKAGE, name)))?,
+            ),
+            None => None,
+        };
+        Ok(self)
+    }
+
+    pub fn build(&self) -> Result<(), RellrError> {
         let output_file_name = self.output_file_name();
-        let mut output = File::create(&output_file_name)?;
-        let config = Self::get_builtin_config().unwrap();
-        let repositories = vec![env::current_dir()?];
-        let mut releases = Vec::<Release>::new();
-        let mut versions = Vec::<String>::new();
-        for repository in repositories {
-            let repository = Repository::init(repository)?;
-            let process_output = self.process_repository(Box::leak(Box::new(repository)), config.clone())?;
-
-            match process_output {
-                ProcessOutput::Releases(release) => releases.extend(release),
-                ProcessOutput::Version(version) => versions.push(version),
+        if self.options().mode.unwrap_or_default() == ChangelogMode::Incremental {
+            if let Ok(existing) = read_to_string(&output_file_name) {
+                let contents = self.prepend(&existing)?;
+                return Ok(write(&output_file_name, contents)?);
             }
         }
 
-        if !versions.is_empty() {
-            let buf = versions.join("\n");
-            output.write_all(buf.as_bytes())?;
-            return Ok(());
+        let config = self.config()?;
+        let releases = self.process_repository(&config, None, Some(self.tag_name()))?;
+        let changelog = GitCliffChangelog::new(releases, &config)?;
+        changelog.generate(&mut File::create(&output_file_name)?)?;
+        Ok(())
+    }
+
+    /// Version that the unreleased commits bump the current version to,
+    /// inferred with the `bump` rules and the version scheme as by `rellr next auto`.
+    pub fn bumped_version(&self) -> Result<String, RellrError> {
+        let config = self.config()?;
+        let commits: Vec<(String, String)> = self
+            .releases(&ChangelogSelection::Unreleased, &config)?
+            .iter()
+            .flat_map(|release| release.commits.iter().map(|commit| (commit.id.clone(), commit.message.clone())))
+            .collect();
+        let scheme = self.project_config.scheme.adapter();
+        let current = &self.project_config.current;
+        let (update_type, _) = self.project_config.bump.infer(scheme.is_initial_development(current), &commits)?;
+        scheme.bump(current, &update_type, None)
+    }
+
+    /// Renders only the section of the release being created, without the header and footer.
+    pub fn section(&self) -> Result<String, RellrError> {
+        let version = self.project_config.next.clone().unwrap_or(self.project_config.current.clone());
+        self.render(&ChangelogSelection::Version(version))
+    }
+
+    /// Renders the selected part of the history without writing any file.
+    /// Only [`ChangelogSelection::All`] includes the header and the footer.
+    pub fn render(&self, selection: &ChangelogSelection) -> Result<String, RellrError> {
+        let mut config = self.config()?;
+        if selection != &ChangelogSelection::All {
+            config.changelog.header = None;
+            config.changelog.footer = None;
         }
+        let releases = self.releases(selection, &config)?;
 
-        let changelog = GitCliffChangelog::new(releases, &config)?;
-        let _ = changelog.generate(&mut output);
+        let mut output = Vec::new();
+        GitCliffChangelog::new(releases, &config)?.generate(&mut output)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
 
-        Ok(())
+    /// Exports the selected releases as JSON, newest first. The commits are parsed, grouped and
+    /// filtered with the same git-cliff configuration as the rendered changelog.
+    pub fn export(&self, selection: &ChangelogSelection) -> Result<String, RellrError> {
+        let config = self.config()?;
+        let skip_regex = config.git.skip_tags.as_ref();
+        let notes: Vec<ReleaseNotes> = self
+            .releases(selection, &config)?
+            .iter()
+            .rev()
+            .map(|release| ReleaseNotes::new(release, &config))
+            .filter(|notes| !notes.groups.is_empty())
+            .filter(|notes| !notes.version.as_ref().is_some_and(|version| skip_regex.is_some_and(|r| r.is_match(version))))
+            .collect();
+        serde_json::to_string_pretty(&notes).map_err(|e| RellrError::Changelog(e.to_string()))
+    }
+
+    /// Releases of the selected part of the history, oldest first, with the commits not yet processed.
+    fn releases(&self, selection: &ChangelogSelection, config: &Config) -> Result<Vec<Release<'_>>, RellrError> {
+        // The commits after the last tag are labelled with the release tag only while it does not exist yet.
+        let untagged = |tag: String| -> Result<Option<String>, RellrError> {
+            let is_tagged = self.repository.tags(&config.git.tag_pattern, false)?.values().any(|name| name == &tag);
+            Ok((!is_tagged).then_some(tag))
+        };
+        let (range, head_tag, version) = match selection {
+            ChangelogSelection::All => (None, untagged(self.tag_name())?, None),
+            ChangelogSelection::Unreleased => (None, None, None),
+            ChangelogSelection::Version(version) => {
+                let tag = self.tag_for(version);
+                (None, untagged(tag.clone())?, Some(tag))
+            }
+            ChangelogSelection::Range(range) => (Some(range.clone()), None, None),
+        };
+
+        let releases = self
+            .process_repository(config, range, head_tag)?
+            .into_iter()
+            .filter(|release| match selection {
+                ChangelogSelection::Unreleased => release.version.is_none(),
+                ChangelogSelection::Version(_) => release.version == version,
+                _ => true,
+            })
+            .collect();
+        Ok(releases)
+    }
+
+    /// Inserts the section of the new release above the first release section of `existing`,
+    /// i.e. below its header and its `## [Unreleased]` section. Nothing is changed when the file already has a section for the version.
+    pub fn prepend(&self, existing: &str) -> Result<String, RellrError> {
+        let version = self.project_config.next.clone().unwrap_or(self.project_config.current.clone());
+        let heading = Regex::new(&format!(r"(?m)^##.*[^0-9A-Za-z.+-]{}($|[^0-9A-Za-z.+-])", regex::escape(&version)))
+            .map_err(|e| RellrError::Changelog(e.to_string()))?;
+        if heading.is_match(existing) {
+            Msg::new(&format!("{} {}", msg::CHANGELOG_SECTION_EXISTS, version)).warn();
+            return Ok(existing.to_string());
+        }
+
+        Ok(insert_section(existing, &self.section()?))
+    }
+
+    pub fn output_file_name(&self) -> String {
+        match &self.package {
+            Some(package) => package.output_file_name(),
+            None => self.options().output_file_name(),
+        }
+    }
+
+    fn options(&self) -> ChangelogOptions {
+        self.project_config.changelog.clone().unwrap_or_default()
+    }
+
+    /// Loads the git-cliff configuration from, in order: the `changelog.config` path, `cliff.toml`
+    /// in the repository, the inline `changelog.cliff` table and the builtin configuration selected by `changelog.template`.
+    /// Unless the configuration sets its own `tag_pattern`, tags are matched with the configured tag template.
+    /// The tags of a package are always matched with the package tag template.
+    fn config(&self) -> Result<Config, RellrError> {
+        let options = self.options();
+        let (mut config, builtin) = match (&options.config, &options.cliff) {
+            (Some(path), _) => (Self::read_config(path)?, false),
+            (None, _) if metadata(REPO_CONFIG).is_ok() => (Self::read_config(REPO_CONFIG)?, false),
+            (None, Some(cliff)) => (self.inline_config(cliff)?, false),
+            (None, None) => (Self::get_builtin_config(options.template.unwrap_or_default())?, true),
+        };
+
+        if let Some(package) = &self.package {
+            config.git.tag_pattern = Some(package.tag_pattern()?);
+        } else if builtin || config.git.tag_pattern.is_none() {
+            config.git.tag_pattern = Some(self.project_config.git.tag_pattern(&self.project_config.name)?);
+        }
+        Ok(config)
+    }
+
+    /// The inline `changelog.cliff` table, read from the configuration file so that errors have its line and column.
+    fn inline_config(&self, cliff: &JsonValue) -> Result<Config, RellrError> {
+        if let Some(config) = self.project_config.config_format.read_at(&["changelog", "cliff"])? {
+            return Ok(config);
+        }
+        serde_json::from_value(cliff.clone()).map_err(|e| RellrError::Config(format!("{}: changelog.cliff: {}", self.project_config.config_file_name(), e)))
+    }
+
+    fn read_config(path: &str) -> Result<Config, RellrError> {
+        let data = read_to_string(path).map_err(|e| RellrError::Config(format!("{}: {}", path, e)))?;
+        toml::from_str(&data).map_err(|e| RellrError::toml(path, e))
+    }
+
+    fn get_builtin_config(template: ChangelogTemplate) -> Result<Config, RellrError> {
+        let (config, name) = match template {
+            ChangelogTemplate::Default => (BUILTIN_CONFIG, BUILTIN_CONFIG_NAME),
+            ChangelogTemplate::KeepAChangelog => (KEEP_A_CHANGELOG_CONFIG, KEEP_A_CHANGELOG_CONFIG_NAME),
+        };
+        toml::from_str(config).map_err(|e| RellrError::toml(name, e))
     }
 
-    pub fn output_file_name(&mut self) -> String {
-        self.project_config.changelog.clone().unwrap_or(DEFAULT_OUTPUT.into())
+    /// Tag of the release being created.
+    fn tag_name(&self) -> String {
+        self.tag_for(self.project_config.next.as_ref().unwrap_or(&self.project_config.current))
     }
 
-    fn get_builtin_config() -> Result<Config> {
-        Ok(toml::from_str(&BUILTIN_CONFIG)?)
+    fn tag_for(&self, version: &str) -> String {
+        match &self.package {
+            Some(package) => package.tag_name(version),
+            None => self.project_config.git.tag_name(&self.project_config.name, version),
+        }
     }
 
-    fn process_repository<'a>(&mut self, repository: &'static Repository, config: Config) -> Result<ProcessOutput<'a>> {
+    /// Groups the commits of `range` (all commits when `None`) into releases.
+    /// A package changelog only keeps the commits that changed the files of the package.
+    /// `head_tag` names the release of the commits after the last tag, which is left unreleased when `None`.
+    fn process_repository(&self, config: &Config, range: Option<String>, head_tag: Option<String>) -> Result<Vec<Release<'_>>, RellrError> {
         let topo_order = false;
-        let mut tags = repository.tags(&config.git.tag_pattern, topo_order)?;
+        let mut tags = self.repository.tags(&config.git.tag_pattern, topo_order)?;
         let skip_regex = config.git.skip_tags.as_ref();
         let ignore_regex = config.git.ignore_tags.as_ref();
         tags = tags
@@ -95,25 +572,26 @@ impl Changelog {
             })
             .collect();
 
-        let mut commits = repository.commits(None, None, None)?;
+        let mut commits = self.repository.commits(range, None, None)?;
         if let Some(commit_limit_value) = config.git.limit_commits {
             commits = commits.drain(..commits.len().min(commit_limit_value)).collect();
         }
 
         // Update tags.
-        let tag = self.project_config.next.as_ref().unwrap_or(&self.project_config.current);
-        if let Some(commit_id) = commits.first().map(|c| c.id().to_string()) {
+        if let (Some(tag), Some(commit_id)) = (head_tag, commits.first().map(|c| c.id().to_string())) {
             match tags.get(&commit_id) {
                 Some(tag) => {
-                    println!("There is already a tag ({}) for {}", tag, commit_id)
+                    Msg::new(&format!("There is already a tag ({}) for {}", tag, commit_id)).warn();
                 }
                 None => {
-                    tags.insert(commit_id, tag.to_string());
+                    tags.insert(commit_id, tag);
                 }
             }
         }
 
-        // Process releases.
+        // Process releases. Package tags may point at commits outside of the package,
+        // so the releases are split on every commit and only then filtered by path.
+        let path_filter = self.package.as_ref().map(|package| package.path_filter()).transpose()?;
         let mut releases = vec![Release::default()];
         let mut release_index = 0;
         let mut previous_release = Release::default();
@@ -121,7 +599,13 @@ impl Changelog {
         for git_commit in commits.into_iter().rev() {
             let commit = Commit::from(&git_commit);
             let commit_id = commit.id.to_string();
-            releases[release_index].commits.push(commit);
+            let in_package = match &path_filter {
+                Some(path_filter) => path_filter.is_match(&self.git.changed_paths(git_commit.id())?),
+                None => true,
+            };
+            if in_package {
+                releases[release_index].commits.push(commit);
+            }
             if let Some(tag) = tags.get(&commit_id) {
                 releases[release_index].version = Some(tag.to_string());
                 releases[release_index].commit_id = Some(commit_id);
@@ -167,6 +651,185 @@ impl Changelog {
             }
         }
 
-        Ok(ProcessOutput::Releases(releases))
+        Ok(releases)
+    }
+}
+
+/// Inserts `section` above the first release heading of `existing`. An `## [Unreleased]` section,
+/// as kept by Keep a Changelog, stays on top.
+fn insert_section(existing: &str, section: &str) -> String {
+    let section = format!("{}\n\n", section.trim());
+    let unreleased = Regex::new(r"(?i)^##\s+\[?unreleased\]?\s*$").expect("valid regex");
+    let position = existing
+        .match_indices("## ")
+        .map(|(index, _)| index)
+        .filter(|&index| index == 0 || existing[..index].ends_with('\n'))
+        .find(|&index| !unreleased.is_match(existing[index..].lines().next().unwrap_or_default()));
+    match position {
+        Some(index) => format!("{}{}{}", &existing[..index], section, &existing[index..]),
+        None if existing.trim().is_empty() => section,
+        None => format!("{}\n\n{}", existing.trim_end(), section),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::config_file::ConfigFormat;
+    use crate::libs::test_repo::TestRepo;
+
+    const SECTION: &str = "## [1.1.0] - 2026-10-18\n\n### Features\n\n- Add a flag\n";
+
+    #[test]
+    fn insert_section_below_header() {
+        let existing = "# Changelog\n\n## [1.0.0] - 2026-01-01\n\n- Initial release\n";
+        let expected = "# Changelog\n\n## [1.1.0] - 2026-10-18\n\n### Features\n\n- Add a flag\n\n## [1.0.0] - 2026-01-01\n\n- Initial release\n";
+        assert_eq!(insert_section(existing, SECTION), expected);
+    }
+
+    #[test]
+    fn insert_section_below_unreleased() {
+        let existing = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\n## [Unreleased]\n\n- Work in progress\n\n## [1.0.0] - 2026-01-01\n\n- Initial release\n\n[Unreleased]: https://example.com/compare/v1.0.0...HEAD\n";
+        let expected = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\n## [Unreleased]\n\n- Work in progress\n\n## [1.1.0] - 2026-10-18\n\n### Features\n\n- Add a flag\n\n## [1.0.0] - 2026-01-01\n\n- Initial release\n\n[Unreleased]: https://example.com/compare/v1.0.0...HEAD\n";
+        assert_eq!(insert_section(existing, SECTION), expected);
+    }
+
+    #[test]
+    fn options_read_the_path_or_the_table() {
+        let options: ChangelogOptions = serde_json::from_str(r#""HISTORY.md""#).unwrap();
+        assert_eq!(options.output_file_name(), "HISTORY.md");
+
+        let options: ChangelogOptions = toml::from_str("output = \"HISTORY.md\"\nmode = \"incremental\"\n").unwrap();
+        assert_eq!(options.output.as_deref(), Some("HISTORY.md"));
+        assert_eq!(options.mode, Some(ChangelogMode::Incremental));
+    }
+
+    #[test]
+    fn options_errors_point_into_the_table() {
+        let error = serde_json::from_str::<ChangelogOptions>("{\n  \"output\": \"HISTORY.md\",\n  \"mode\": \"incremntal\"\n}").unwrap_err();
+        assert!(error.to_string().starts_with("unknown variant `incremntal`, expected `full` or `incremental`"), "{}", error);
+        assert_eq!(error.line(), 3);
+
+        let error = serde_json::from_str::<ChangelogOptions>("true").unwrap_err();
+        assert_eq!(error.to_string(), "invalid type: boolean `true`, expected the path of the changelog or a table at line 1 column 4");
+    }
+
+    #[test]
+    fn insert_section_after_unreleased_only() {
+        let existing = "# Changelog\n\n## Unreleased\n";
+        assert_eq!(insert_section(existing, SECTION), format!("# Changelog\n\n## Unreleased\n\n{}\n\n", SECTION.trim()));
+    }
+
+    #[test]
+    fn builds_and_renders_twice_in_one_process() {
+        let repo = TestRepo::new("changelog-twice");
+        repo.write("notes.txt", "one\n");
+        repo.commit("feat: add the notes");
+        repo.git(&["tag", "v1.0.0"]);
+        repo.write("notes.txt", "two\n");
+        repo.commit("fix: update the notes");
+        let mut config = ProjectConfig::new("demo", Some("1.0.0".into()), ConfigFormat::Json);
+        config.next = Some("1.0.1".into());
+
+        for _ in 0..2 {
+            let changelog = Changelog::open(&config).unwrap();
+            changelog.build().unwrap();
+            let built = repo.read(DEFAULT_OUTPUT);
+            assert!(built.contains("[1.0.1]") && built.contains("[1.0.0]"), "{}", built);
+
+            let section = changelog.section().unwrap();
+            assert!(section.contains("[1.0.1]") && section.contains("Update the notes"), "{}", section);
+            assert!(!section.contains("[1.0.0]"), "{}", section);
+        }
+    }
+
+    #[test]
+    fn export_groups_the_commits_of_each_release() {
+        let repo = TestRepo::new("changelog-export");
+        repo.write("notes.txt", "one\n");
+        repo.commit("fix: handle errors");
+        repo.write("notes.txt", "two\n");
+        repo.commit("feat(api): add a flag");
+        repo.git(&["tag", "v1.0.0"]);
+        repo.write("notes.txt", "three\n");
+        repo.commit("feat!: drop the old flag");
+        let id = |revision: &str| repo.git(&["rev-parse", revision]).trim().to_string();
+        let config = ProjectConfig::new("demo", Some("1.0.0".into()), ConfigFormat::Json);
+
+        let export = Changelog::open(&config).unwrap().export(&ChangelogSelection::All).unwrap();
+        let mut notes: JsonValue = serde_json::from_str(&export).unwrap();
+        let date = notes[1]["date"].take();
+        assert!(Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap().is_match(date.as_str().unwrap()), "{}", date);
+        let commit = |revision: &str, message: &str, commit_type: &str, scope: Option<&str>, breaking: bool| {
+            serde_json::json!({ "id": id(revision), "message": message, "type": commit_type, "scope": scope, "breaking": breaking })
+        };
+        let expected = serde_json::json!([
+            {
+                "version": null,
+                "date": null,
+                "commit_id": null,
+                "groups": [{ "name": "✨ Features", "commits": [commit("HEAD", "drop the old flag", "feat", None, true)] }],
+            },
+            {
+                "version": "v1.0.0",
+                "date": null,
+                "commit_id": id("v1.0.0"),
+                "groups": [
+                    { "name": "🛠️ Bug Fixes", "commits": [commit("v1.0.0~1", "handle errors", "fix", None, false)] },
+                    { "name": "✨ Features", "commits": [commit("v1.0.0", "add a flag", "feat", Some("api"), false)] },
+                ],
+            },
+        ]);
+        assert_eq!(notes, expected);
+    }
+
+    fn package(include: &[&str], exclude: &[&str]) -> ChangelogPackage {
+        ChangelogPackage {
+            name: "a".into(),
+            path: "packages/a/".into(),
+            include: include.iter().map(|glob| glob.to_string()).collect(),
+            exclude: exclude.iter().map(|glob| glob.to_string()).collect(),
+            output: None,
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn path_filter_matches_the_files_of_the_package() {
+        let paths = |paths: &[&str]| -> Vec<String> { paths.iter().map(|path| path.to_string()).collect() };
+        let filter = package(&[], &["**/*.md"]).path_filter().unwrap();
+        assert!(filter.is_match(&paths(&["README.md", "packages/a/src/lib.rs"])));
+        assert!(!filter.is_match(&paths(&["packages/a/CHANGELOG.md", "packages/ab/src/lib.rs"])));
+
+        let filter = package(&["packages/a/src/**", "shared/**"], &[]).path_filter().unwrap();
+        assert!(filter.is_match(&paths(&["shared/util.rs"])));
+        assert!(!filter.is_match(&paths(&["packages/a/README.md"])));
+    }
+
+    #[test]
+    fn package_changelog_keeps_the_commits_of_the_package() {
+        let repo = TestRepo::new("changelog-package");
+        repo.write("packages/a/lib.rs", "");
+        repo.write("packages/b/lib.rs", "");
+        repo.commit("feat: add the packages");
+        repo.git(&["tag", "a@1.0.0"]);
+        repo.write("packages/a/lib.rs", "// a\n");
+        repo.commit("fix: change a");
+        repo.write("packages/b/lib.rs", "// b\n");
+        repo.commit("fix: change b");
+        repo.write("packages/a/lib.rs", "// a\n// a\n");
+        repo.write("packages/b/lib.rs", "// b\n// b\n");
+        repo.commit("feat: change both");
+        let mut config = ProjectConfig::new("demo", Some("1.0.0".into()), ConfigFormat::Json);
+        config.next = Some("1.1.0".into());
+        config.changelog = Some(ChangelogOptions {
+            packages: vec![package(&[], &[])],
+            ..ChangelogOptions::default()
+        });
+
+        let section = Changelog::open(&config).unwrap().with_package(Some("a")).unwrap().section().unwrap();
+        assert!(section.contains("[a@1.1.0]"), "{}", section);
+        assert!(section.contains("Change a") && section.contains("Change both"), "{}", section);
+        assert!(!section.contains("- Change b\n") && !section.contains("Add the packages"), "{}", section);
     }
 }
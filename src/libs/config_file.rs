@@ -0,0 +1,376 @@
+use super::error::RellrError;
+use super::json_edit;
+use clap::ValueEnum;
+use serde::de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use std::fmt;
+use std::fs::{metadata, read_to_string, write};
+use std::marker::PhantomData;
+use toml_edit::{Document, Item, Table};
+
+pub const JSON_CONFIG: &str = "rellr.json";
+pub const TOML_CONFIG: &str = "rellr.toml";
+pub const CARGO_CONFIG: &str = "Cargo.toml";
+const CARGO_METADATA_PATH: [&str; 3] = ["package", "metadata", "rellr"];
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Cargo,
+}
+
+impl ConfigFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => JSON_CONFIG,
+            ConfigFormat::Toml => TOML_CONFIG,
+            ConfigFormat::Cargo => CARGO_CONFIG,
+        }
+    }
+
+    /// Finds the configuration in the current directory: `rellr.toml`, then `rellr.json`,
+    /// then a `[package.metadata.rellr]` table in `Cargo.toml`.
+    pub fn discover() -> Option<Self> {
+        [ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Cargo].into_iter().find(|format| format.exists())
+    }
+
+    pub fn exists(&self) -> bool {
+        match self {
+            ConfigFormat::Cargo => read_to_string(CARGO_CONFIG)
+                .ok()
+                .and_then(|data| data.parse::<Document>().ok())
+                .is_some_and(|document| Self::cargo_metadata(document.as_table()).is_some()),
+            _ => metadata(self.file_name()).is_ok(),
+        }
+    }
+
+    pub fn read<T: DeserializeOwned>(&self) -> Result<T, RellrError> {
+        let file_name = self.file_name();
+        let data = read_to_string(file_name)?;
+        let value = match self {
+            ConfigFormat::Json => serde_json::from_str(&data).map_err(|e| RellrError::json(file_name, e))?,
+            ConfigFormat::Toml => toml::from_str(&data).map_err(|e| RellrError::toml(file_name, e))?,
+            ConfigFormat::Cargo => {
+                // Deserialize the whole manifest so that error locations point into Cargo.toml.
+                let cargo: CargoManifest<T> = toml::from_str(&data).map_err(|e| RellrError::toml(file_name, e))?;
+                cargo.package.metadata.rellr
+            }
+        };
+        Ok(value)
+    }
+
+    /// Reads the value at `keys` of the configuration, e.g. `changelog.cliff`, `None` when the file or the key is missing.
+    /// The value is deserialized while the file is parsed, so errors point into the file.
+    pub fn read_at<T: DeserializeOwned>(&self, keys: &[&str]) -> Result<Option<T>, RellrError> {
+        let file_name = self.file_name();
+        let Ok(data) = read_to_string(file_name) else {
+            return Ok(None);
+        };
+        let value = match self {
+            ConfigFormat::Json => Nested::new(keys).deserialize(&mut serde_json::Deserializer::from_str(&data)).map_err(|e| RellrError::json(file_name, e))?,
+            ConfigFormat::Toml => Nested::new(keys).deserialize(toml::Deserializer::new(&data)).map_err(|e| RellrError::toml(file_name, e))?,
+            ConfigFormat::Cargo => {
+                let keys = [CARGO_METADATA_PATH.as_slice(), keys].concat();
+                Nested::new(&keys).deserialize(toml::Deserializer::new(&data)).map_err(|e| RellrError::toml(file_name, e))?
+            }
+        };
+        Ok(value)
+    }
+
+    /// Writes the value back into the configuration file, keeping the layout of the existing file:
+    /// key order, indentation and (for TOML) comments of untouched entries are preserved.
+    /// Only the entries that `T` reads are set or removed; other entries of the file are kept.
+    pub fn write<T: Serialize + DeserializeOwned>(&self, value: &T) -> Result<(), RellrError> {
+        let contents = self.render(value)?;
+        write(self.file_name(), contents)?;
+        Ok(())
+    }
+
+    /// Current contents of the configuration file, empty when it does not exist yet.
+    pub fn contents(&self) -> String {
+        read_to_string(self.file_name()).unwrap_or_default()
+    }
+
+    /// Renders the contents that [`write`](ConfigFormat::write) would store, without touching the file.
+    pub fn render<T: Serialize + DeserializeOwned>(&self, value: &T) -> Result<String, RellrError> {
+        let file_name = self.file_name();
+        let data = self.contents();
+        // The entries written for the value read from the file are the ones that the merge may remove.
+        let previous = match self.read::<T>() {
+            Ok(previous) => serde_json::to_value(previous).map_err(|e| RellrError::Config(e.to_string()))?,
+            Err(_) => JsonValue::Null,
+        };
+        let contents = match self {
+            ConfigFormat::Json => {
+                let mut target: JsonValue = match data.trim().is_empty() {
+                    true => JsonValue::Null,
+                    false => serde_json::from_str(&data).map_err(|e| RellrError::json(file_name, e))?,
+                };
+                let source = serde_json::to_value(value).map_err(|e| RellrError::Config(e.to_string()))?;
+                merge_json_value(&mut target, source, &previous);
+                json_edit::edit(&data, &target)
+            }
+            ConfigFormat::Toml => {
+                let mut document = data.parse::<Document>().map_err(|e| RellrError::toml(file_name, e))?;
+                merge_toml_table(document.as_table_mut(), Self::to_toml(value)?.as_table(), &previous);
+                document.to_string()
+            }
+            ConfigFormat::Cargo => {
+                if data.is_empty() {
+                    return Err(RellrError::Config(format!("{} is missing", file_name)));
+                }
+                let mut document = data.parse::<Document>().map_err(|e| RellrError::toml(file_name, e))?;
+                let mut table = document.as_table_mut();
+                for key in CARGO_METADATA_PATH {
+                    let item = table.entry(key).or_insert_with(|| {
+                        let mut table = Table::new();
+                        table.set_implicit(true);
+                        Item::Table(table)
+                    });
+                    table = item.as_table_mut().ok_or(RellrError::Config(format!("`{}` is not a table in {}", key, file_name)))?;
+                }
+                table.set_implicit(false);
+                merge_toml_table(table, Self::to_toml(value)?.as_table(), &previous);
+                document.to_string()
+            }
+        };
+        Ok(contents)
+    }
+
+    fn to_toml<T: Serialize>(value: &T) -> Result<Document, RellrError> {
+        let data = toml::to_string(value).map_err(|e| RellrError::Config(e.to_string()))?;
+        data.parse::<Document>().map_err(|e| RellrError::Config(e.to_string()))
+    }
+
+    fn cargo_metadata(table: &Table) -> Option<&Table> {
+        let mut table = table;
+        for key in CARGO_METADATA_PATH {
+            table = table.get(key)?.as_table()?;
+        }
+        Some(table)
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoManifest<T> {
+    package: CargoPackage<T>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage<T> {
+    metadata: CargoMetadata<T>,
+}
+
+#[derive(Deserialize)]
+struct CargoMetadata<T> {
+    rellr: T,
+}
+
+/// Deserializes the value found by following `keys` through nested tables; other values are skipped.
+struct Nested<'a, T> {
+    keys: &'a [&'a str],
+    value: PhantomData<T>,
+}
+
+impl<'a, T> Nested<'a, T> {
+    fn new(keys: &'a [&'a str]) -> Self {
+        Self { keys, value: PhantomData }
+    }
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for Nested<'_, T> {
+    type Value = Option<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match self.keys.is_empty() {
+            true => T::deserialize(deserializer).map(Some),
+            false => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for Nested<'_, T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a table with `{}`", self.keys.join("."))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut value = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key == self.keys[0] {
+                true => value = map.next_value_seed(Nested::new(&self.keys[1..]))?,
+                false => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Merges `source` into `target`, keeping the key order of `target`. A key missing in `source` is removed only when
+/// `previous`, the value that `target` was read as, has it: the other keys are not read by rellr and are kept.
+fn merge_json_value(target: &mut JsonValue, source: JsonValue, previous: &JsonValue) {
+    match (target, source) {
+        (JsonValue::Object(target), JsonValue::Object(source)) => {
+            target.retain(|key, _| source.contains_key(key) || previous.get(key).is_none());
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json_value(existing, value, &previous[key.as_str()]),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (JsonValue::Array(target), JsonValue::Array(source)) if target.len() == source.len() => {
+            for (index, (existing, value)) in target.iter_mut().zip(source).enumerate() {
+                merge_json_value(existing, value, &previous[index]);
+            }
+        }
+        (target, source) => *target = source,
+    }
+}
+
+/// Merges `source` into `target` like [`merge_json_value`], keeping the decor (comments and whitespace)
+/// of unchanged and updated entries.
+fn merge_toml_table(target: &mut Table, source: &Table, previous: &JsonValue) {
+    let removed: Vec<String> = target
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !source.contains_key(key) && previous.get(key).is_some())
+        .collect();
+    for key in removed {
+        target.remove(&key);
+    }
+
+    for (key, item) in source.iter() {
+        match target.get_mut(key) {
+            Some(existing) => merge_toml_item(existing, item, &previous[key]),
+            None => {
+                target.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn merge_toml_item(target: &mut Item, source: &Item, previous: &JsonValue) {
+    match (target, source) {
+        (Item::Table(target), Item::Table(source)) => merge_toml_table(target, source, previous),
+        (Item::ArrayOfTables(target), Item::ArrayOfTables(source)) if target.len() == source.len() => {
+            for (index, (existing, table)) in target.iter_mut().zip(source.iter()).enumerate() {
+                merge_toml_table(existing, table, &previous[index]);
+            }
+        }
+        (Item::Value(target), Item::Table(source)) if target.is_inline_table() => {
+            merge_toml_value(target, &toml_edit::Value::InlineTable(source.clone().into_inline_table()), previous)
+        }
+        (Item::Value(target), Item::Value(source)) => merge_toml_value(target, source, previous),
+        (target, source) => *target = source.clone(),
+    }
+}
+
+fn merge_toml_value(target: &mut toml_edit::Value, source: &toml_edit::Value, previous: &JsonValue) {
+    match (target, source) {
+        (toml_edit::Value::InlineTable(target), toml_edit::Value::InlineTable(source)) => {
+            let removed: Vec<String> = target
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .filter(|key| !source.contains_key(key) && previous.get(key).is_some())
+                .collect();
+            for key in removed {
+                target.remove(&key);
+            }
+            for (key, value) in source.iter() {
+                match target.get_mut(key) {
+                    Some(existing) => merge_toml_value(existing, value, &previous[key]),
+                    None => {
+                        target.insert(key, value.clone());
+                    }
+                }
+            }
+        }
+        (toml_edit::Value::Array(target), toml_edit::Value::Array(source)) if target.len() == source.len() => {
+            for (index, (existing, value)) in target.iter_mut().zip(source.iter()).enumerate() {
+                merge_toml_value(existing, value, &previous[index]);
+            }
+        }
+        (target, source) => {
+            if normalized(target) != normalized(source) {
+                let decor = target.decor().clone();
+                *target = source.clone();
+                *target.decor_mut() = decor;
+            }
+        }
+    }
+}
+
+fn normalized(value: &toml_edit::Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    if let Some(table) = value.as_inline_table_mut() {
+        table.fmt();
+    }
+    if let Some(array) = value.as_array_mut() {
+        array.fmt();
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Git {
+        conventional_commits: bool,
+    }
+
+    #[test]
+    fn merge_json_keeps_keys_that_are_not_read() {
+        let data = "{\n  \"$schema\": \"./schema.json\",\n  \"current\": \"1.0.0\",\n  \"next\": \"1.1.0\",\n  \"git\": { \"note\": \"x\", \"tag\": \"v{version}\" }\n}\n";
+        let mut target: JsonValue = serde_json::from_str(data).unwrap();
+        let previous = serde_json::json!({ "current": "1.0.0", "next": "1.1.0", "git": { "tag": "v{version}" } });
+        let source = serde_json::json!({ "current": "1.1.0", "git": { "tag": "v{version}" } });
+        merge_json_value(&mut target, source, &previous);
+        let expected = "{\n  \"$schema\": \"./schema.json\",\n  \"current\": \"1.1.0\",\n  \"git\": { \"note\": \"x\", \"tag\": \"v{version}\" }\n}\n";
+        assert_eq!(json_edit::edit(data, &target), expected);
+    }
+
+    #[test]
+    fn merge_toml_keeps_keys_that_are_not_read() {
+        let data = "# release settings\ncurrent = \"1.0.0\" # bumped by rellr\nnext = \"1.1.0\"\nowner = \"team\"\npackage_managers = [{ type = \"npm\", path = \"web\", note = \"x\" }]\n";
+        let mut document = data.parse::<Document>().unwrap();
+        let previous = serde_json::json!({ "current": "1.0.0", "next": "1.1.0", "package_managers": [{ "type": "npm", "path": "web" }] });
+        let source = "current = \"1.1.0\"\npackage_managers = [{ type = \"npm\", path = \"app\" }]\n".parse::<Document>().unwrap();
+        merge_toml_table(document.as_table_mut(), source.as_table(), &previous);
+        let expected = "# release settings\ncurrent = \"1.1.0\" # bumped by rellr\nowner = \"team\"\npackage_managers = [{ type = \"npm\", path = \"app\", note = \"x\" }]\n";
+        assert_eq!(document.to_string(), expected);
+    }
+
+    #[test]
+    fn nested_reads_the_value_at_keys() {
+        let data = "{\n  \"name\": \"x\",\n  \"changelog\": { \"cliff\": { \"conventional_commits\": true } }\n}";
+        let value: Option<Git> = Nested::new(&["changelog", "cliff"]).deserialize(&mut serde_json::Deserializer::from_str(data)).unwrap();
+        assert_eq!(value, Some(Git { conventional_commits: true }));
+
+        let value: Option<Git> = Nested::new(&["changelog", "cliff"]).deserialize(toml::Deserializer::new("name = \"x\"\n")).unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn nested_errors_point_into_the_document() {
+        let data = "{\n  \"changelog\": {\n    \"cliff\": { \"conventional_commits\": \"yes\" }\n  }\n}";
+        let error = Nested::<Git>::new(&["changelog", "cliff"]).deserialize(&mut serde_json::Deserializer::from_str(data)).unwrap_err();
+        assert_eq!((error.line(), error.column()), (3, 44));
+
+        let data = "[changelog.cliff]\nconventional_commits = \"yes\"\n";
+        let error = Nested::<Git>::new(&["changelog", "cliff"]).deserialize(toml::Deserializer::new(data)).unwrap_err();
+        assert!(error.to_string().contains("line 2, column 24"), "{}", error);
+    }
+}
@@ -0,0 +1,98 @@
+use super::msg;
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RellrError {
+    #[error("{0}")]
+    Config(String),
+    #[error("{0}")]
+    Git(String),
+    /// The merge stopped with conflicts, which were written to the index.
+    #[error("{}\n{}\n{}", msg::MERGE_CONFLICTS, .0.join("\n"), msg::MERGE_CONFLICTS_HELP)]
+    MergeConflicts(Vec<String>),
+    #[error("{0}")]
+    Version(String),
+    #[error("{0}")]
+    Changelog(String),
+    #[error("{0}")]
+    PackageManager(String),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+impl RellrError {
+    /// Process exit code reported for each kind of failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RellrError::Config(_) => 2,
+            RellrError::Git(_) | RellrError::MergeConflicts(_) => 3,
+            RellrError::Version(_) => 4,
+            RellrError::Changelog(_) => 5,
+            RellrError::PackageManager(_) => 6,
+            RellrError::Io(_) => 7,
+        }
+    }
+
+    /// Config error pointing at the file, line and column where the JSON could not be parsed.
+    pub fn json(file_name: &str, error: serde_json::Error) -> Self {
+        let message = error.to_string();
+        let suffix = format!(" at line {} column {}", error.line(), error.column());
+        let message = message.strip_suffix(&suffix).unwrap_or(&message);
+        RellrError::Config(format!("{}:{}:{}: {}", file_name, error.line(), error.column(), message))
+    }
+
+    /// Config error for a TOML document; the message of the parser already contains the line and column.
+    pub fn toml(file_name: &str, error: impl ToString) -> Self {
+        RellrError::Config(format!("{}: {}", file_name, error.to_string().trim_end()))
+    }
+}
+
+impl From<git2::Error> for RellrError {
+    fn from(error: git2::Error) -> Self {
+        RellrError::Git(error.message().to_string())
+    }
+}
+
+impl From<git_cliff_core::error::Error> for RellrError {
+    fn from(error: git_cliff_core::error::Error) -> Self {
+        RellrError::Changelog(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_of_each_kind() {
+        let errors = [
+            (RellrError::Config("".into()), 2),
+            (RellrError::Git("".into()), 3),
+            (RellrError::MergeConflicts(vec!["a.txt".into()]), 3),
+            (RellrError::Version("".into()), 4),
+            (RellrError::Changelog("".into()), 5),
+            (RellrError::PackageManager("".into()), 6),
+            (RellrError::Io(io::Error::other("")), 7),
+        ];
+        for (error, code) in errors {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn json_error_points_at_the_file_line_and_column() {
+        let error = serde_json::from_str::<serde_json::Value>("{\n  \"name\": \"demo\",\n  \"current\": 1.0.0\n}").unwrap_err();
+        let error = RellrError::json("rellr.json", error);
+        assert!(matches!(error, RellrError::Config(_)));
+        assert_eq!(error.to_string(), "rellr.json:3:17: expected `,` or `}`");
+    }
+
+    #[test]
+    fn toml_error_keeps_the_position_of_the_parser() {
+        let error = toml::from_str::<toml::Table>("name = \"demo\"\ncurrent = 1.0.0\n").unwrap_err();
+        let message = RellrError::toml("rellr.toml", error).to_string();
+        assert!(message.starts_with("rellr.toml: TOML parse error at line 2, column"), "{}", message);
+        assert!(!message.ends_with('\n'));
+    }
+}
@@ -1,9 +1,64 @@
 use super::{
+    error::RellrError,
+    helpers::render_template,
     msg::{self, Msg},
     project_config::ProjectConfig,
 };
-use git2::{ObjectType, Reference, Repository, RepositoryInitOptions, Signature};
-use std::{fmt, path::Path};
+use git2::build::CheckoutBuilder;
+use git2::{AnnotatedCommit, CheckoutNotificationType, ErrorCode, ObjectType, Oid, Reference, Repository, RepositoryInitOptions, Signature, StashSaveOptions, StatusOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{read, remove_file, write},
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_MERGE_MESSAGE: &str = "Merge branch '{branch}' into {main_branch}";
+pub const DEFAULT_TAG: &str = "v{version}";
+pub const DEFAULT_COMMIT_MESSAGE: &str = "{version}";
+
+/// The `git` section of the configuration.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Message of the merge commit created when the main branch has moved on since the release branch was created.
+    /// Supports the `{branch}`, `{main_branch}` and `{version}` placeholders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_message: Option<String>,
+    /// Name of the release tag, e.g. `{name}@{version}` or `release-{version}`.
+    /// Supports the `{name}` and `{version}` placeholders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Message of the release commit and the tag. Supports the `{name}`, `{version}` and `{tag}` placeholders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+}
+
+impl GitConfig {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn tag_name(&self, name: &str, version: &str) -> String {
+        render_template(self.tag.as_deref().unwrap_or(DEFAULT_TAG), &[("name", name), ("version", version)])
+    }
+
+    pub fn commit_message(&self, name: &str, version: &str) -> String {
+        let tag = self.tag_name(name, version);
+        render_template(
+            self.commit_message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE),
+            &[("name", name), ("version", version), ("tag", &tag)],
+        )
+    }
+
+    /// Pattern matching the tags created with the tag template, used to find the releases in the history.
+    pub fn tag_pattern(&self, name: &str) -> Result<Regex, RellrError> {
+        let pattern = regex::escape(self.tag.as_deref().unwrap_or(DEFAULT_TAG))
+            .replace(r"\{name\}", &regex::escape(name))
+            .replace(r"\{version\}", "[0-9].*");
+        Regex::new(&format!("^{}$", pattern)).map_err(|e| RellrError::Config(e.to_string()))
+    }
+}
 
 #[derive(Debug, Default, Clone)]
 pub enum BranchType {
@@ -13,12 +68,36 @@ pub enum BranchType {
     Hotfix,
 }
 
+impl BranchType {
+    /// Full name of a branch of this type, e.g. `release/1.2.0`.
+    pub fn branch_name(&self, name: &str) -> String {
+        format!("{}/{}", self.to_string().to_lowercase(), name)
+    }
+}
+
 impl fmt::Display for BranchType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
+/// How the release branch was brought into the main branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    FastForward,
+    Normal,
+    UpToDate,
+}
+
+/// The release commit and the tags pointing at it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitOutcome {
+    pub commit_id: String,
+    pub tag: String,
+    /// Tags of the changelog packages.
+    pub package_tags: Vec<String>,
+}
+
 #[allow(dead_code)]
 enum VersionType {
     Main,
@@ -28,45 +107,81 @@ enum VersionType {
 pub struct Git {
     pub project_config: ProjectConfig,
     pub repo: Repository,
+    /// Stash local changes that would be overwritten by a checkout instead of refusing it.
+    pub stash: bool,
+    /// Paths that are never stashed, e.g. the changelog written by the release being created.
+    pub keep: Vec<String>,
 }
 
 impl Git {
-    pub fn new(project_config: &ProjectConfig) -> Self {
+    pub fn new(project_config: &ProjectConfig) -> Result<Self, RellrError> {
         let repo_path = ".";
-        let open_repo = Repository::open(&repo_path);
+        let open_repo = Repository::open(repo_path);
         let repo: Repository = match open_repo {
             Ok(repo) => repo,
-            _ => Self::init(&repo_path, &project_config.main_branch).unwrap(),
+            _ => Self::init(repo_path, &project_config.main_branch)?,
         };
 
-        Self {
+        Ok(Self {
             project_config: project_config.clone(),
-            repo: repo,
-        }
+            repo,
+            stash: false,
+            keep: vec![],
+        })
+    }
+
+    /// Opens the repository in the current directory without initializing a new one.
+    pub fn open(project_config: &ProjectConfig) -> Result<Self, RellrError> {
+        Ok(Self {
+            project_config: project_config.clone(),
+            repo: Repository::open(".")?,
+            stash: false,
+            keep: vec![],
+        })
+    }
+
+    pub fn with_stash(mut self, stash: bool) -> Self {
+        self.stash = stash;
+        self
+    }
+
+    pub fn with_kept_paths(mut self, paths: Vec<String>) -> Self {
+        self.keep = paths.iter().map(|path| path.trim_start_matches("./").to_string()).collect();
+        self
+    }
+
+    pub fn exists() -> bool {
+        Repository::open(".").is_ok()
+    }
+
+    /// Whether `path`, relative to the current directory, is in the index of the repository there.
+    pub fn is_tracked(path: &str) -> bool {
+        let index = Repository::open(".").and_then(|repo| repo.index());
+        index.is_ok_and(|index| index.get_path(Path::new(path), 0).is_some())
     }
 
     pub fn init(repo_path: &str, main_branch: &str) -> Result<Repository, git2::Error> {
         let mut init_options = RepositoryInitOptions::new();
-        init_options.initial_head(&main_branch);
-        let repo: Repository = Repository::init_opts(&repo_path, &init_options)?;
+        init_options.initial_head(main_branch);
+        let repo: Repository = Repository::init_opts(repo_path, &init_options)?;
 
         Ok(repo)
     }
 
-    pub fn add_branch(&mut self, name: &str) -> Result<Self, git2::Error> {
-        let branch_name = self.get_branch_name(&name);
+    pub fn add_branch(&mut self, name: &str) -> Result<Self, RellrError> {
+        let branch_name = self.get_branch_name(name);
         let branch = self.repo.find_branch(&branch_name, git2::BranchType::Local);
         if branch.is_err() {
             let main_branch = &self.repo.find_branch(&self.project_config.main_branch, git2::BranchType::Local)?;
             self.repo.branch(&branch_name, &main_branch.get().peel_to_commit()?, false)?;
         }
 
-        Ok(Self::new(&self.project_config))
+        self.reopen()
     }
 
-    pub fn add_or_rename_next_branch(&mut self) -> Result<Self, git2::Error> {
+    pub fn add_or_rename_next_branch(&mut self) -> Result<Self, RellrError> {
         if self.next_branch_name().is_none() {
-            Msg::new(msg::RELEASE_VERSION_NOT_SET).error().exit()
+            return Err(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()));
         }
 
         if self.prev_branch_name().is_some() {
@@ -76,40 +191,206 @@ impl Git {
         self.add_next_branch()
     }
 
-    fn add_next_branch(&mut self) -> Result<Self, git2::Error> {
-        self.add_branch(&self.project_config.next.clone().unwrap())
+    /// Creates the branch from the main branch and switches to it; a branch created here is deleted again when the checkout fails.
+    pub fn add_branch_and_checkout(&mut self, name: &str) -> Result<(), RellrError> {
+        let branch_name = self.get_branch_name(name);
+        let is_new = !self.has_branch(&branch_name);
+        let mut git = self.add_branch(name)?;
+        let checkout = git.checkout(Some(name));
+        if checkout.is_err() && is_new {
+            let _ = git.repo.find_branch(&branch_name, git2::BranchType::Local).and_then(|mut branch| branch.delete());
+        }
+        checkout
+    }
+
+    fn add_next_branch(&mut self) -> Result<Self, RellrError> {
+        let next = self.project_config.next.clone().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))?;
+        self.add_branch(&next)
     }
 
-    fn rename_next_branch(&mut self) -> Result<Self, git2::Error> {
-        let prev_branch_name = self.prev_branch_name().unwrap();
-        let next_branch_name = self.next_branch_name().unwrap();
+    fn rename_next_branch(&mut self) -> Result<Self, RellrError> {
+        let prev_branch_name = self.prev_branch_name().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))?;
+        let next_branch_name = self.next_branch_name().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))?;
         let prev_branch = self.repo.find_branch(&prev_branch_name, git2::BranchType::Local);
         if prev_branch.is_err() {
-            return Self::new(&self.project_config).add_next_branch();
+            return self.reopen()?.add_next_branch();
         }
 
         let _ = prev_branch?.rename(&next_branch_name, false);
-        Ok(Self::new(&self.project_config))
+        self.reopen()
     }
 
-    pub fn checkout(&mut self, name: Option<&str>) -> Result<(), git2::Error> {
-        let main_branch_ref = self.get_branch_ref_name(VersionType::Main);
+    pub fn checkout(&mut self, name: Option<&str>) -> Result<(), RellrError> {
+        let main_branch_ref = self.get_branch_ref_name(VersionType::Main)?;
         let ref_name: String = match name {
             Some(name) => {
-                let branch_name = self.get_branch_name(&name);
+                let branch_name = self.get_branch_name(name);
                 let branch_ref = self.repo.find_branch(&branch_name, git2::BranchType::Local)?;
                 branch_ref.get().name().unwrap_or(&main_branch_ref).to_owned()
             }
             None => main_branch_ref,
         };
 
+        let target = self.repo.find_reference(&ref_name)?.peel_to_commit()?.id();
+        self.checkout_tree(target)?;
         self.repo.set_head(&ref_name)?;
 
         Ok(())
     }
 
-    pub fn commit(&mut self, paths: Vec<&str>) -> Result<(), git2::Error> {
-        let version = self.project_config.next.clone().unwrap();
+    /// Updates the index and the working tree to the commit like `git switch`: local changes are kept,
+    /// unless the checkout would overwrite them. Those are stashed with [`stash`](Git::stash), except the [`keep`](Git::keep) paths,
+    /// or the checkout is refused.
+    fn checkout_tree(&mut self, target: Oid) -> Result<(), RellrError> {
+        let mut conflicts = self.try_checkout_tree(target)?;
+        let stashed: Vec<String> = conflicts.iter().filter(|path| !self.keep.contains(path)).cloned().collect();
+        if !stashed.is_empty() && self.stash {
+            let stash_id = self.stash_paths(&stashed)?;
+            Msg::new(&format!("{} {}", msg::CHANGES_STASHED, stash_id)).warn();
+            conflicts = self.try_checkout_tree(target)?;
+        }
+
+        if !conflicts.is_empty() {
+            return Err(RellrError::Git(format!(
+                "{}\n{}\n{}",
+                msg::CHECKOUT_CONFLICTS,
+                conflicts.join("\n"),
+                msg::CHECKOUT_CONFLICTS_HELP
+            )));
+        }
+        Ok(())
+    }
+
+    /// Stashes the local changes of `paths`. libgit2 resets the whole working tree after a partial stash,
+    /// so the other local changes are written back afterwards.
+    fn stash_paths(&mut self, paths: &[String]) -> Result<Oid, RellrError> {
+        let workdir = self.repo.workdir().ok_or(RellrError::Git(msg::BARE_REPOSITORY.into()))?.to_path_buf();
+        let mut changes: Vec<(PathBuf, Option<Vec<u8>>)> = vec![];
+        for entry in self.repo.statuses(Some(StatusOptions::new().include_untracked(false)))?.iter() {
+            match entry.path() {
+                Some(path) if !paths.iter().any(|stashed| stashed == path) && !entry.status().is_empty() => {
+                    let path = workdir.join(path);
+                    changes.push((path.clone(), read(&path).ok()));
+                }
+                _ => {}
+            }
+        }
+
+        let signature = self.get_signature()?.to_owned();
+        let mut options = StashSaveOptions::new(signature);
+        for path in paths {
+            options.pathspec(path.as_str());
+        }
+        let stash_id = self.repo.stash_save_ext(Some(&mut options))?;
+
+        for (path, contents) in changes {
+            match contents {
+                Some(contents) => write(&path, contents)?,
+                None => {
+                    let _ = remove_file(&path);
+                }
+            }
+        }
+        Ok(stash_id)
+    }
+
+    /// Safe checkout of the commit; returns the paths of local changes that prevent it.
+    fn try_checkout_tree(&self, target: Oid) -> Result<Vec<String>, RellrError> {
+        let commit = self.repo.find_object(target, Some(ObjectType::Commit))?;
+        let mut conflicts: Vec<String> = vec![];
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe().notify_on(CheckoutNotificationType::CONFLICT).notify(|_, path, _, _, _| {
+            if let Some(path) = path {
+                conflicts.push(path.to_string_lossy().into_owned());
+            }
+            true
+        });
+
+        match self.repo.checkout_tree(&commit, Some(&mut checkout)) {
+            Ok(()) => Ok(vec![]),
+            Err(err) if err.code() == ErrorCode::Conflict => {
+                drop(checkout);
+                Ok(conflicts)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn has_branch(&self, branch_name: &str) -> bool {
+        self.repo.find_branch(branch_name, git2::BranchType::Local).is_ok()
+    }
+
+    pub fn tag_name(&self, version: &str) -> String {
+        self.project_config.git.tag_name(&self.project_config.name, version)
+    }
+
+    pub fn commit_message(&self, version: &str) -> String {
+        self.project_config.git.commit_message(&self.project_config.name, version)
+    }
+
+    pub fn package_tag_names(&self, version: &str) -> Vec<String> {
+        self.project_config.changelog.clone().unwrap_or_default().package_tags(version)
+    }
+
+    /// Commits reachable from HEAD since the last release tag, newest first, as `(id, message)` pairs.
+    pub fn commits_since_last_tag(&self) -> Result<Vec<(String, String)>, RellrError> {
+        let tag_pattern = self.project_config.git.tag_pattern(&self.project_config.name)?;
+        let mut tagged: Vec<Oid> = vec![];
+        for name in self.repo.tag_names(None)?.iter().flatten().filter(|name| tag_pattern.is_match(name)) {
+            let reference = self.repo.find_reference(&format!("refs/tags/{}", name))?;
+            tagged.push(reference.peel_to_commit()?.id());
+        }
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push_head()?;
+        let last_tag = revwalk.by_ref().flatten().find(|oid| tagged.contains(oid));
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push_head()?;
+        if let Some(last_tag) = last_tag {
+            revwalk.hide(last_tag)?;
+        }
+
+        let mut commits: Vec<(String, String)> = vec![];
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push((commit.id().to_string(), commit.message().unwrap_or_default().to_string()));
+        }
+        Ok(commits)
+    }
+
+    /// Paths added, changed or deleted by a commit, compared to its first parent.
+    pub fn changed_paths(&self, commit_id: Oid) -> Result<Vec<String>, RellrError> {
+        let commit = self.repo.find_commit(commit_id)?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = self.repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        Ok(diff
+            .deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    /// Message of the merge commit of the release branch into the main branch.
+    pub fn merge_message(&self, version: &str) -> String {
+        let template = self.project_config.git.merge_message.as_deref().unwrap_or(DEFAULT_MERGE_MESSAGE);
+        render_template(
+            template,
+            &[
+                ("branch", &self.project_config.branch_type.branch_name(version)),
+                ("main_branch", &self.project_config.main_branch),
+                ("version", version),
+            ],
+        )
+    }
+
+    pub fn commit(&mut self, paths: Vec<&str>, version: &str) -> Result<CommitOutcome, RellrError> {
         let mut index = self.repo.index()?;
 
         for path in paths {
@@ -120,54 +401,115 @@ impl Git {
         let tree_id = index.write_tree()?;
         let tree = self.repo.find_tree(tree_id)?;
 
-        let mut git = Self::new(&self.project_config);
+        let mut git = Self::new(&self.project_config)?;
         let signature = git.get_signature()?;
         let head = self.repo.head()?;
-        let parent_commit = self.repo.find_commit(head.target().unwrap())?;
+        let parent_commit = head.peel_to_commit()?;
 
-        let commit_id = self.repo.commit(Some("HEAD"), &signature, &signature, &version, &tree, &[&parent_commit])?;
+        let message = self.commit_message(version);
+        let commit_id = self.repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&parent_commit])?;
         let commit = self.repo.find_object(commit_id, Some(ObjectType::Commit))?;
 
-        let tag = format!("v{}", &version);
-        self.repo.tag(&tag, &commit, &signature, &version, false)?;
+        let tag = self.tag_name(version);
+        self.repo.tag(&tag, &commit, &signature, &message, false)?;
+        let package_tags = self.package_tag_names(version);
+        for package_tag in &package_tags {
+            self.repo.tag(package_tag, &commit, &signature, &message, false)?;
+        }
 
-        Ok(())
+        Ok(CommitOutcome {
+            commit_id: commit_id.to_string(),
+            tag,
+            package_tags,
+        })
     }
 
-    pub fn checkout_next(&mut self) -> Result<(), git2::Error> {
+    pub fn checkout_next(&mut self) -> Result<(), RellrError> {
         self.checkout(self.project_config.next.clone().as_deref())
     }
 
-    pub fn merge(&mut self) -> Result<Self, git2::Error> {
-        self.checkout(None)?;
+    /// Determines how the release branch would be merged, without changing anything.
+    pub fn merge_analysis(&mut self) -> Result<MergeOutcome, RellrError> {
+        if self.next_branch_name().is_none() {
+            return Err(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()));
+        }
+
+        let next_branch_ref_name = self.get_branch_ref_name(VersionType::Next)?;
+        let main_branch_ref_name = self.get_branch_ref_name(VersionType::Main)?;
+        let branch_ref = self.repo.find_reference(&next_branch_ref_name)?;
+        let selected_commit = self.repo.reference_to_annotated_commit(&branch_ref)?;
+        let main_ref = self.repo.find_reference(&main_branch_ref_name)?;
+        let analysis = self.repo.merge_analysis_for_ref(&main_ref, &[&selected_commit])?;
 
+        if analysis.0.is_fast_forward() {
+            Ok(MergeOutcome::FastForward)
+        } else if analysis.0.is_normal() {
+            Ok(MergeOutcome::Normal)
+        } else if analysis.0.is_up_to_date() {
+            Ok(MergeOutcome::UpToDate)
+        } else {
+            Err(RellrError::Git(msg::MERGE_CONFLICTS.into()))
+        }
+    }
+
+    pub fn merge(&mut self) -> Result<MergeOutcome, RellrError> {
         if self.next_branch_name().is_none() {
-            Msg::new(msg::RELEASE_VERSION_NOT_SET).error().exit()
+            return Err(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()));
         }
 
-        let repo = Self::new(&self.project_config).repo;
+        self.checkout(None)?;
+
+        let repo = Self::new(&self.project_config)?.repo;
 
-        let next_branch_ref_name = self.get_branch_ref_name(VersionType::Next);
-        let next_branch_name = self.next_branch_name().unwrap();
+        let next_branch_ref_name = self.get_branch_ref_name(VersionType::Next)?;
+        let next_branch_name = self.next_branch_name().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))?;
 
         let mut branch_ref = repo.find_reference(&next_branch_ref_name)?;
         let selected_commit = repo.reference_to_annotated_commit(&branch_ref)?;
         let analysis = repo.merge_analysis(&[&selected_commit])?;
 
-        if analysis.0.is_fast_forward() {
+        let outcome = if analysis.0.is_fast_forward() {
             self.fast_forward(&mut branch_ref)?;
-            Msg::new("Fast-Forward").info();
+            MergeOutcome::FastForward
         } else if analysis.0.is_normal() {
-            Msg::new("Normal merge").info();
+            self.normal_merge(&repo, &selected_commit)?;
+            MergeOutcome::Normal
         } else if analysis.0.is_up_to_date() {
-            Msg::new("The repository is up to date and no merge is required").info();
+            MergeOutcome::UpToDate
         } else {
-            Msg::new("Merge conflicts were detected! Resolve conflicts and commit manually").error().exit()
-        }
+            return Err(RellrError::Git(msg::MERGE_CONFLICTS.into()));
+        };
 
         self.repo.find_branch(&next_branch_name, git2::BranchType::Local)?.delete()?;
 
-        Ok(Self::new(&self.project_config))
+        Ok(outcome)
+    }
+
+    /// Three-way merge of the release branch into the checked out main branch, creating a merge commit.
+    /// Conflicts are left in the index and the working tree for the user to resolve.
+    fn normal_merge(&mut self, repo: &Repository, their: &AnnotatedCommit) -> Result<(), RellrError> {
+        repo.merge(&[their], None, None)?;
+
+        let mut index = repo.index()?;
+        if index.has_conflicts() {
+            let paths = index
+                .conflicts()?
+                .filter_map(|conflict| conflict.ok())
+                .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                .collect();
+            return Err(RellrError::MergeConflicts(paths));
+        }
+
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let head_commit = repo.head()?.peel_to_commit()?;
+        let their_commit = repo.find_commit(their.id())?;
+        let message = self.merge_message(&self.project_config.next.clone().unwrap_or_default());
+        let signature = self.get_signature()?;
+        repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&head_commit, &their_commit])?;
+        repo.cleanup_state()?;
+
+        Ok(())
     }
 
     fn fast_forward(&mut self, branch_ref: &mut Reference) -> Result<(), git2::Error> {
@@ -178,47 +520,48 @@ impl Git {
 
         let main_branch = self.repo.find_branch(&self.project_config.main_branch, git2::BranchType::Local)?;
         let target_ref = main_branch.into_reference();
-        let target_oid = target_ref.target().unwrap();
+        let target_name = target_ref.name().ok_or(git2::Error::from_str("invalid main branch name"))?.to_string();
+        let target_oid = target_ref.peel_to_commit()?.id();
 
-        let source_oid = branch_ref.target().unwrap();
+        // The reference may come from another handle on the repository, so the commit is looked up in this one.
+        let source_oid = branch_ref.peel(ObjectType::Commit)?.id();
         let source_commit = self.repo.find_object(source_oid, Some(ObjectType::Commit))?;
 
         let log_msg = format!("Fast-Forward: Setting {} to id: {}", &name, &source_oid);
 
         if self.repo.graph_ahead_behind(source_oid, target_oid)?.0 > 0 {
             self.repo.checkout_tree(&source_commit, None)?;
-            self.repo.reference(&target_ref.name().unwrap(), source_commit.id(), true, &log_msg)?;
+            self.repo.reference(&target_name, source_oid, true, &log_msg)?;
         }
 
         Ok(())
     }
 
+    fn reopen(&self) -> Result<Self, RellrError> {
+        Ok(Self::new(&self.project_config)?.with_stash(self.stash).with_kept_paths(self.keep.clone()))
+    }
+
     fn next_branch_name(&mut self) -> Option<String> {
-        match self.project_config.next {
-            Some(_) => Some(self.get_branch_name(&self.project_config.next.clone().unwrap())),
-            _ => None,
-        }
+        let next = self.project_config.next.clone()?;
+        Some(self.get_branch_name(&next))
     }
 
     fn prev_branch_name(&mut self) -> Option<String> {
-        match self.project_config.prev {
-            Some(_) => Some(self.get_branch_name(&self.project_config.prev.clone().unwrap())),
-            _ => None,
-        }
+        let prev = self.project_config.prev.clone()?;
+        Some(self.get_branch_name(&prev))
     }
 
     fn get_branch_name(&mut self, name: &str) -> String {
-        format!("{}/{}", self.project_config.branch_type.to_string().to_lowercase(), &name)
+        self.project_config.branch_type.branch_name(name)
     }
 
-    fn get_branch_ref_name(&mut self, version_type: VersionType) -> String {
-        let next_branch_name = self.next_branch_name().unwrap();
+    fn get_branch_ref_name(&mut self, version_type: VersionType) -> Result<String, RellrError> {
         let name = match version_type {
-            VersionType::Main => &self.project_config.main_branch,
-            VersionType::Next => &next_branch_name,
+            VersionType::Main => self.project_config.main_branch.clone(),
+            VersionType::Next => self.next_branch_name().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))?,
         };
 
-        format!("refs/heads/{}", name)
+        Ok(format!("refs/heads/{}", name))
     }
 
     fn get_signature(&mut self) -> Result<Signature<'_>, git2::Error> {
@@ -230,3 +573,84 @@ impl Git {
         Ok(signature)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::config_file::ConfigFormat;
+    use crate::libs::test_repo::TestRepo;
+
+    /// A repository whose `release/1.1.0` branch changed `release_file` while `main` changed `main_file`.
+    fn diverged(name: &str, release_file: &str, main_file: &str) -> (TestRepo, ProjectConfig) {
+        let repo = TestRepo::new(name);
+        repo.write("notes.txt", "one\n");
+        repo.commit("feat: initial commit");
+        repo.git(&["checkout", "-q", "-b", "release/1.1.0"]);
+        repo.write(release_file, "release\n");
+        repo.commit("1.1.0");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.write(main_file, "main\n");
+        repo.commit("fix: on main");
+        let mut config = ProjectConfig::new("demo", Some("1.0.0".into()), ConfigFormat::Json);
+        config.next = Some("1.1.0".into());
+        (repo, config)
+    }
+
+    #[test]
+    fn tag_and_commit_message_templates() {
+        let config = GitConfig::default();
+        assert_eq!(config.tag_name("demo", "1.2.0"), "v1.2.0");
+        assert_eq!(config.commit_message("demo", "1.2.0"), "1.2.0");
+
+        let config = GitConfig {
+            tag: Some("{name}@{version}".into()),
+            commit_message: Some("chore(release): {tag}".into()),
+            ..GitConfig::default()
+        };
+        assert_eq!(config.tag_name("demo", "1.2.0"), "demo@1.2.0");
+        assert_eq!(config.commit_message("demo", "1.2.0"), "chore(release): demo@1.2.0");
+    }
+
+    #[test]
+    fn tag_pattern_matches_only_the_tags_of_the_template() {
+        let pattern = GitConfig::default().tag_pattern("demo").unwrap();
+        assert!(pattern.is_match("v1.2.0") && pattern.is_match("v1.2.0-beta.0"));
+        assert!(!pattern.is_match("1.2.0") && !pattern.is_match("v-next"));
+
+        let config = GitConfig {
+            tag: Some("{name}@{version}".into()),
+            ..GitConfig::default()
+        };
+        let pattern = config.tag_pattern("my.pkg").unwrap();
+        assert!(pattern.is_match("my.pkg@1.2.0"));
+        assert!(!pattern.is_match("myxpkg@1.2.0") && !pattern.is_match("other@1.2.0") && !pattern.is_match("v1.2.0"));
+    }
+
+    #[test]
+    fn merge_creates_a_merge_commit_when_main_moved_on() {
+        let (repo, config) = diverged("git-merge", "release.txt", "main.txt");
+        let mut git = Git::open(&config).unwrap();
+        assert_eq!(git.merge_analysis().unwrap(), MergeOutcome::Normal);
+        assert_eq!(git.merge().unwrap(), MergeOutcome::Normal);
+
+        let parents = repo.git(&["rev-list", "--parents", "-n", "1", "HEAD"]);
+        assert_eq!(parents.split_whitespace().count(), 3);
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "Merge branch 'release/1.1.0' into main\n");
+        assert_eq!(repo.git(&["branch", "--list", "release/*"]), "");
+        assert_eq!(repo.read("release.txt"), "release\n");
+        assert_eq!(repo.read("main.txt"), "main\n");
+        assert_eq!(repo.git(&["status", "--short"]), "");
+    }
+
+    #[test]
+    fn merge_reports_the_conflicting_paths() {
+        let (repo, config) = diverged("git-merge-conflicts", "notes.txt", "notes.txt");
+        let mut git = Git::open(&config).unwrap();
+        match git.merge() {
+            Err(RellrError::MergeConflicts(paths)) => assert_eq!(paths, ["notes.txt"]),
+            result => panic!("expected conflicts, got {:?}", result.map_err(|e| e.to_string())),
+        }
+        assert_eq!(repo.git(&["status", "--short"]), "UU notes.txt\n");
+        assert!(repo.git(&["branch", "--list", "release/*"]).contains("release/1.1.0"));
+    }
+}
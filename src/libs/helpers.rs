@@ -15,10 +15,100 @@ pub fn to_path_str(parts: Vec<&str>) -> String {
 pub fn check_files_existence(files: Vec<&str>) -> Vec<String> {
     let mut non_existent_files = vec![];
     for file in files {
-        if !fs::metadata(file).is_ok() {
+        if fs::metadata(file).is_err() {
             non_existent_files.push(file.to_string());
         }
     }
 
     non_existent_files
 }
+
+/// Line-based unified diff of two texts with two lines of context around each change.
+pub fn diff_lines(before: &str, after: &str) -> String {
+    const CONTEXT: usize = 2;
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    // Longest common subsequence table.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    // (old line index, new line index, prefix, text)
+    let mut lines: Vec<(usize, usize, char, &str)> = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((i, j, ' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push((i, j, '-', old[i]));
+            i += 1;
+        } else {
+            lines.push((i, j, '+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = lines.iter().enumerate().filter(|(_, line)| line.2 != ' ').map(|(index, _)| index).collect();
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = String::new();
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|line| line.2 != '+').count();
+        let new_count = hunk.iter().filter(|line| line.2 != '-').count();
+        output.push_str(&format!("@@ -{},{} +{},{} @@\n", hunk[0].0 + 1, old_count, hunk[0].1 + 1, new_count));
+        for (_, _, prefix, text) in hunk {
+            output.push_str(&format!("{}{}\n", prefix, text));
+        }
+    }
+    output
+}
+
+/// Replaces `{key}` placeholders in the template with the given values.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |result, (key, value)| result.replace(&format!("{{{}}}", key), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines_of_equal_texts_is_empty() {
+        assert_eq!(diff_lines("a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn diff_lines_keeps_two_lines_of_context() {
+        let before = "1\n2\n3\n4\n5\n6\n7\n";
+        let after = "1\n2\n3\nfour\n5\n6\n7\n";
+        assert_eq!(diff_lines(before, after), "@@ -2,5 +2,5 @@\n 2\n 3\n-4\n+four\n 5\n 6\n");
+    }
+
+    #[test]
+    fn diff_lines_splits_distant_changes_into_hunks() {
+        let before = "a\n1\n2\n3\n4\n5\n6\nb\n";
+        let after = "A\n1\n2\n3\n4\n5\n6\nB\nc\n";
+        let expected = "@@ -1,3 +1,3 @@\n-a\n+A\n 1\n 2\n@@ -6,3 +6,4 @@\n 5\n 6\n-b\n+B\n+c\n";
+        assert_eq!(diff_lines(before, after), expected);
+    }
+}
@@ -0,0 +1,261 @@
+use super::error::RellrError;
+use super::msg;
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Repository, RepositoryState};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, read_to_string, remove_file, write};
+use std::io;
+use std::path::PathBuf;
+
+const JOURNAL_FILE: &str = "rellr-journal.json";
+const INDEX_BACKUP_FILE: &str = "rellr-journal.index";
+
+/// Steps of a release that change the repository, in the order they are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Changelog,
+    Merge,
+    UpdateVersions,
+    Commit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefState {
+    pub name: String,
+    /// Target before the release, `None` when the ref did not exist.
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileState {
+    pub path: String,
+    /// Contents before the release, `None` when the file did not exist.
+    pub contents: Option<String>,
+}
+
+/// Record of every mutation made by a release, kept in the git directory until the release
+/// finishes so that an interrupted or failed release can be rolled back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    pub version: String,
+    pub head: Option<String>,
+    pub head_commit: Option<String>,
+    pub steps: Vec<Step>,
+    pub refs: Vec<RefState>,
+    pub files: Vec<FileState>,
+}
+
+impl Journal {
+    /// Starts a new journal; fails when an interrupted release has not been resolved yet.
+    pub fn begin(repo: &Repository, version: &str) -> Result<Self, RellrError> {
+        if Self::path(repo).exists() {
+            return Err(RellrError::Git(msg::RELEASE_IN_PROGRESS.into()));
+        }
+
+        let head = repo.find_reference("HEAD").ok().and_then(|head| head.symbolic_target().map(String::from));
+        let head_commit = repo.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string());
+        let index_path = repo.path().join("index");
+        if index_path.exists() {
+            fs::copy(&index_path, Self::index_backup_path(repo))?;
+        }
+
+        let journal = Self {
+            version: version.into(),
+            head,
+            head_commit,
+            steps: vec![],
+            refs: vec![],
+            files: vec![],
+        };
+        journal.save(repo)?;
+        Ok(journal)
+    }
+
+    pub fn load(repo: &Repository) -> Result<Option<Self>, RellrError> {
+        let path = Self::path(repo);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = read_to_string(&path)?;
+        let journal = serde_json::from_str(&data).map_err(|e| RellrError::json(&path.to_string_lossy(), e))?;
+        Ok(Some(journal))
+    }
+
+    pub fn save(&self, repo: &Repository) -> Result<(), RellrError> {
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        write(Self::path(repo), data)?;
+        Ok(())
+    }
+
+    /// Removes the journal once the release has been completed.
+    pub fn finish(repo: &Repository) -> Result<(), RellrError> {
+        for path in [Self::path(repo), Self::index_backup_path(repo)] {
+            if path.exists() {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_completed(&self, step: Step) -> bool {
+        self.steps.contains(&step)
+    }
+
+    pub fn complete(&mut self, repo: &Repository, step: Step) -> Result<(), RellrError> {
+        if !self.is_completed(step) {
+            self.steps.push(step);
+        }
+        self.save(repo)
+    }
+
+    /// Remembers the target of a ref before it is first changed.
+    pub fn record_ref(&mut self, repo: &Repository, name: &str) -> Result<(), RellrError> {
+        if self.refs.iter().any(|state| state.name == name) {
+            return Ok(());
+        }
+        let target = repo.find_reference(name).ok().and_then(|reference| reference.target()).map(|oid| oid.to_string());
+        self.refs.push(RefState { name: name.into(), target });
+        self.save(repo)
+    }
+
+    /// Remembers the contents of a file before it is first written.
+    pub fn record_file(&mut self, repo: &Repository, path: &str) -> Result<(), RellrError> {
+        if self.files.iter().any(|state| state.path == path) {
+            return Ok(());
+        }
+        self.files.push(FileState {
+            path: path.into(),
+            contents: read_to_string(path).ok(),
+        });
+        self.save(repo)
+    }
+
+    /// Restores refs, HEAD, the working tree, the index and the recorded files, then removes the journal.
+    pub fn rollback(&self, repo: &Repository) -> Result<(), RellrError> {
+        let current_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+
+        for state in self.refs.iter().rev() {
+            match &state.target {
+                Some(target) => {
+                    repo.reference(&state.name, Oid::from_str(target)?, true, "rellr: rollback")?;
+                }
+                None => {
+                    if let Ok(mut reference) = repo.find_reference(&state.name) {
+                        reference.delete()?;
+                    }
+                }
+            }
+        }
+
+        if let Some(head) = &self.head {
+            repo.set_head(head)?;
+        }
+
+        // Drop a merge stopped by conflicts, like `git merge --abort`.
+        if repo.state() != RepositoryState::Clean {
+            repo.cleanup_state()?;
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        }
+
+        // Bring back the paths that differ between the released and the original HEAD.
+        let original_tree = self.head_commit.as_ref().and_then(|oid| repo.find_commit(Oid::from_str(oid).ok()?).ok()).and_then(|commit| commit.tree().ok());
+        if let (Some(current_tree), Some(original_tree)) = (&current_tree, &original_tree) {
+            let diff = repo.diff_tree_to_tree(Some(current_tree), Some(original_tree), None)?;
+            let paths: Vec<PathBuf> = diff
+                .deltas()
+                .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+                .flatten()
+                .map(PathBuf::from)
+                .collect();
+            if !paths.is_empty() {
+                let mut checkout = CheckoutBuilder::new();
+                checkout.force().remove_untracked(false);
+                for path in &paths {
+                    checkout.path(path);
+                }
+                repo.checkout_tree(original_tree.as_object(), Some(&mut checkout))?;
+            }
+        }
+
+        for state in &self.files {
+            match &state.contents {
+                Some(contents) => write(&state.path, contents)?,
+                None => {
+                    if fs::metadata(&state.path).is_ok() {
+                        remove_file(&state.path)?;
+                    }
+                }
+            }
+        }
+
+        let index_backup = Self::index_backup_path(repo);
+        if index_backup.exists() {
+            fs::copy(&index_backup, repo.path().join("index"))?;
+        }
+
+        Self::finish(repo)
+    }
+
+    fn path(repo: &Repository) -> PathBuf {
+        repo.path().join(JOURNAL_FILE)
+    }
+
+    fn index_backup_path(repo: &Repository) -> PathBuf {
+        repo.path().join(INDEX_BACKUP_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::test_repo::TestRepo;
+
+    #[test]
+    fn rollback_restores_refs_head_files_and_index() {
+        let repo = TestRepo::new("journal-rollback");
+        repo.write("notes.txt", "one\n");
+        repo.commit("feat: initial commit");
+        let main = repo.git(&["rev-parse", "main"]);
+        repo.write("staged.txt", "staged\n");
+        repo.git(&["add", "staged.txt"]);
+
+        let git = Repository::open(&repo.root).unwrap();
+        let mut journal = Journal::begin(&git, "1.1.0").unwrap();
+        journal.record_ref(&git, "refs/heads/release/1.1.0").unwrap();
+        journal.record_ref(&git, "refs/heads/main").unwrap();
+        journal.record_file(&git, "notes.txt").unwrap();
+        journal.record_file(&git, "CHANGELOG.md").unwrap();
+        repo.git(&["checkout", "-q", "-b", "release/1.1.0"]);
+        repo.write("notes.txt", "two\n");
+        repo.write("CHANGELOG.md", "# Changelog\n");
+        // Only the release files are committed, the staged file stays in the index.
+        repo.git(&["add", "notes.txt", "CHANGELOG.md"]);
+        repo.git(&["commit", "-q", "-m", "1.1.0", "--", "notes.txt", "CHANGELOG.md"]);
+        repo.git(&["checkout", "-q", "main"]);
+        repo.git(&["merge", "-q", "--ff-only", "release/1.1.0"]);
+        repo.git(&["checkout", "-q", "release/1.1.0"]);
+        assert!(Journal::load(&git).unwrap().is_some());
+
+        journal.rollback(&git).unwrap();
+        assert_eq!(repo.git(&["rev-parse", "main"]), main);
+        assert_eq!(repo.git(&["branch", "--list", "release/*"]), "");
+        assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/main\n");
+        assert_eq!(repo.read("notes.txt"), "one\n");
+        assert!(!repo.root.join("CHANGELOG.md").exists());
+        assert_eq!(repo.git(&["status", "--short"]), "A  staged.txt\n");
+        assert!(Journal::load(&git).unwrap().is_none());
+    }
+
+    #[test]
+    fn begin_fails_while_a_release_is_in_progress() {
+        let repo = TestRepo::new("journal-in-progress");
+        repo.write("notes.txt", "one\n");
+        repo.commit("feat: initial commit");
+        let git = Repository::open(&repo.root).unwrap();
+        Journal::begin(&git, "1.1.0").unwrap();
+        assert!(matches!(Journal::begin(&git, "1.1.0"), Err(RellrError::Git(_))));
+        Journal::finish(&git).unwrap();
+        assert!(Journal::begin(&git, "1.1.0").is_ok());
+    }
+}
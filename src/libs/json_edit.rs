@@ -0,0 +1,314 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::ops::Range;
+
+/// A value of a JSON document with the byte range of its text.
+enum Node {
+    Object { range: Range<usize>, members: Vec<Member> },
+    Array { range: Range<usize>, items: Vec<Node> },
+    Scalar { range: Range<usize> },
+}
+
+/// A member of an object; its range goes from the opening quote of the key to the end of the value.
+struct Member {
+    key: String,
+    range: Range<usize>,
+    value: Node,
+}
+
+impl Node {
+    fn range(&self) -> &Range<usize> {
+        match self {
+            Node::Object { range, .. } | Node::Array { range, .. } | Node::Scalar { range } => range,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object { members, .. } => members.iter().find(|member| member.key == key).map(|member| &member.value),
+            _ => None,
+        }
+    }
+}
+
+/// Byte range, quotes included, of the string found by following `keys` through nested objects.
+pub fn string_span(data: &str, keys: &[&str]) -> Option<Range<usize>> {
+    let mut node = &parse(data)?;
+    for key in keys {
+        node = node.get(key)?;
+    }
+    match node {
+        Node::Scalar { range } if data[range.clone()].starts_with('"') => Some(range.clone()),
+        _ => None,
+    }
+}
+
+/// Rewrites `data` so that it holds `value`, replacing only the values that differ. The layout of the
+/// rest of the document is kept: inline objects stay inline, added members follow the layout of their siblings.
+/// When `data` is not a JSON document, e.g. an empty file, the whole value is pretty-printed.
+pub fn edit(data: &str, value: &Value) -> String {
+    let (Some(node), Ok(current)) = (parse(data), serde_json::from_str::<Value>(data)) else {
+        let mut contents = pretty(value, data);
+        if data.ends_with('\n') {
+            contents.push('\n');
+        }
+        return contents;
+    };
+    let mut edits: Vec<(Range<usize>, String)> = vec![];
+    diff(data, &node, &current, value, &mut edits);
+    // Insertions come before a deletion that starts at the same byte.
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+    let mut contents = data.to_string();
+    for (range, text) in edits.into_iter().rev() {
+        contents.replace_range(range, &text);
+    }
+    contents
+}
+
+fn diff(data: &str, node: &Node, current: &Value, value: &Value, edits: &mut Vec<(Range<usize>, String)>) {
+    if current == value {
+        return;
+    }
+    match (node, current, value) {
+        (Node::Object { members, .. }, Value::Object(current), Value::Object(object)) if members.iter().any(|member| object.contains_key(&member.key)) => {
+            // Runs of removed members are deleted with the separator before or after them.
+            let mut index = 0;
+            while index < members.len() {
+                if object.contains_key(&members[index].key) {
+                    index += 1;
+                    continue;
+                }
+                let start = index;
+                while index < members.len() && !object.contains_key(&members[index].key) {
+                    index += 1;
+                }
+                let range = match (members.get(index), start.checked_sub(1)) {
+                    (Some(next), _) => members[start].range.start..next.range.start,
+                    (None, Some(previous)) => members[previous].range.end..members[index - 1].range.end,
+                    (None, None) => members[start].range.start..members[index - 1].range.end,
+                };
+                edits.push((range, String::new()));
+            }
+
+            let kept: Vec<&Member> = members.iter().filter(|member| object.contains_key(&member.key)).collect();
+            for member in &kept {
+                diff(data, &member.value, &current[&member.key], &object[&member.key], edits);
+            }
+
+            let Some(last) = kept.last() else {
+                return;
+            };
+            let separator = match members.len() {
+                1 => format!(",{}", &data[node.range().start + 1..members[0].range.start]),
+                _ => data[members[0].range.end..members[1].range.start].to_string(),
+            };
+            let added: String = object
+                .iter()
+                .filter(|(key, _)| !current.contains_key(*key))
+                .map(|(key, value)| format!("{}{}: {}", separator, Value::String(key.clone()), render(data, node.range(), last.range.start, value)))
+                .collect();
+            if !added.is_empty() {
+                edits.push((last.range.end..last.range.end, added));
+            }
+        }
+        (Node::Array { items, .. }, Value::Array(current), Value::Array(array)) if current.len() == array.len() => {
+            for ((item, current), value) in items.iter().zip(current).zip(array) {
+                diff(data, item, current, value, edits);
+            }
+        }
+        (node, _, value) => edits.push((node.range().clone(), render(data, node.range(), node.range().start, value))),
+    }
+}
+
+/// Text of `value` written at `position`: on one line when the text at `layout` is on one line,
+/// pretty-printed and indented like the line of `position` otherwise.
+fn render(data: &str, layout: &Range<usize>, position: usize, value: &Value) -> String {
+    let text = &data[layout.clone()];
+    if !text.contains('\n') {
+        let padding = match text.starts_with("{ ") || text.starts_with("[ ") {
+            true => " ",
+            false => "",
+        };
+        return inline(value, padding);
+    }
+
+    let line_start = data[..position].rfind('\n').map_or(0, |index| index + 1);
+    let line = &data[line_start..position];
+    let indent = &line[..line.len() - line.trim_start().len()];
+    pretty(value, data).replace('\n', &format!("\n{}", indent))
+}
+
+/// `value` pretty-printed with the indentation unit of `data`.
+fn pretty(value: &Value, data: &str) -> String {
+    let indent = json_indent(data);
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    // Serializing a `serde_json::Value` into memory cannot fail.
+    value.serialize(&mut serializer).expect("JSON value is serializable");
+    String::from_utf8(buf).expect("JSON output is valid UTF-8")
+}
+
+/// `value` on one line, with `padding` inside non-empty braces and brackets.
+fn inline(value: &Value, padding: &str) -> String {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            let members: Vec<String> = object.iter().map(|(key, value)| format!("{}: {}", Value::String(key.clone()), inline(value, padding))).collect();
+            format!("{{{}{}{}}}", padding, members.join(", "), padding)
+        }
+        Value::Array(array) if !array.is_empty() => {
+            let items: Vec<String> = array.iter().map(|item| inline(item, padding)).collect();
+            format!("[{}{}{}]", padding, items.join(", "), padding)
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Detects the indentation used by an existing JSON document.
+fn json_indent(data: &str) -> String {
+    data.lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+        .to_string()
+}
+
+fn parse(data: &str) -> Option<Node> {
+    let bytes = data.as_bytes();
+    let mut position = 0;
+    skip_whitespace(bytes, &mut position);
+    let node = parse_value(data, &mut position)?;
+    skip_whitespace(bytes, &mut position);
+    (position == bytes.len()).then_some(node)
+}
+
+fn parse_value(data: &str, position: &mut usize) -> Option<Node> {
+    let bytes = data.as_bytes();
+    let start = *position;
+    match bytes.get(start)? {
+        b'{' => {
+            *position += 1;
+            let mut members: Vec<Member> = vec![];
+            loop {
+                skip_whitespace(bytes, position);
+                if members.is_empty() && bytes.get(*position) == Some(&b'}') {
+                    break;
+                }
+                let key = skip_string(bytes, position)?;
+                let key_start = key.start;
+                let key: String = serde_json::from_str(&data[key]).ok()?;
+                skip_whitespace(bytes, position);
+                if bytes.get(*position) != Some(&b':') {
+                    return None;
+                }
+                *position += 1;
+                skip_whitespace(bytes, position);
+                let value = parse_value(data, position)?;
+                members.push(Member {
+                    key,
+                    range: key_start..value.range().end,
+                    value,
+                });
+                skip_whitespace(bytes, position);
+                match bytes.get(*position)? {
+                    b',' => *position += 1,
+                    b'}' => break,
+                    _ => return None,
+                }
+            }
+            *position += 1;
+            Some(Node::Object { range: start..*position, members })
+        }
+        b'[' => {
+            *position += 1;
+            let mut items: Vec<Node> = vec![];
+            loop {
+                skip_whitespace(bytes, position);
+                if items.is_empty() && bytes.get(*position) == Some(&b']') {
+                    break;
+                }
+                items.push(parse_value(data, position)?);
+                skip_whitespace(bytes, position);
+                match bytes.get(*position)? {
+                    b',' => *position += 1,
+                    b']' => break,
+                    _ => return None,
+                }
+            }
+            *position += 1;
+            Some(Node::Array { range: start..*position, items })
+        }
+        b'"' => Some(Node::Scalar {
+            range: skip_string(bytes, position)?,
+        }),
+        _ => {
+            while bytes.get(*position).is_some_and(|byte| !byte.is_ascii_whitespace() && !b",:{}[]".contains(byte)) {
+                *position += 1;
+            }
+            (*position > start).then_some(Node::Scalar { range: start..*position })
+        }
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], position: &mut usize) {
+    while bytes.get(*position).is_some_and(|byte| byte.is_ascii_whitespace()) {
+        *position += 1;
+    }
+}
+
+/// Skips the string at `position` and returns its range, `None` when there is no string.
+fn skip_string(bytes: &[u8], position: &mut usize) -> Option<Range<usize>> {
+    let start = *position;
+    if bytes.get(start) != Some(&b'"') {
+        return None;
+    }
+    let mut end = start + 1;
+    loop {
+        match bytes.get(end)? {
+            b'\\' => end += 2,
+            b'"' => break,
+            _ => end += 1,
+        }
+    }
+    *position = end + 1;
+    Some(start..end + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn edit_keeps_inline_entries() {
+        let data = "{\n  \"current\": \"0.0.0\",\n  \"next\": \"0.0.1\",\n  \"package_managers\": [\n    \"cargo\",\n    { \"type\": \"npm\", \"path\": \"npm\", \"publish\": true }\n  ]\n}\n";
+        let value = json!({
+            "current": "0.0.1",
+            "package_managers": ["cargo", { "type": "npm", "path": "npm", "publish": true }],
+        });
+        let expected = "{\n  \"current\": \"0.0.1\",\n  \"package_managers\": [\n    \"cargo\",\n    { \"type\": \"npm\", \"path\": \"npm\", \"publish\": true }\n  ]\n}\n";
+        assert_eq!(edit(data, &value), expected);
+    }
+
+    #[test]
+    fn edit_adds_members_like_their_siblings() {
+        let data = "{\n    \"current\": \"1.0.0\",\n    \"main_branch\": \"main\"\n}";
+        let value = json!({ "current": "1.0.0", "main_branch": "main", "next": "1.1.0", "tag": { "format": "v{version}" } });
+        let expected = "{\n    \"current\": \"1.0.0\",\n    \"main_branch\": \"main\",\n    \"next\": \"1.1.0\",\n    \"tag\": {\n        \"format\": \"v{version}\"\n    }\n}";
+        assert_eq!(edit(data, &value), expected);
+    }
+
+    #[test]
+    fn edit_removes_trailing_members() {
+        let data = r#"{ "current": "1.0.0", "prev": "0.9.0", "next": "1.0.1" }"#;
+        let value = json!({ "current": "1.0.1" });
+        assert_eq!(edit(data, &value), r#"{ "current": "1.0.1" }"#);
+    }
+
+    #[test]
+    fn edit_pretty_prints_a_new_document() {
+        let value = json!({ "name": "demo", "current": "0.0.0" });
+        assert_eq!(edit("", &value), "{\n  \"name\": \"demo\",\n  \"current\": \"0.0.0\"\n}");
+    }
+}
@@ -1,5 +1,14 @@
 pub mod changelog;
+pub mod config_file;
+pub mod error;
 pub mod git;
 pub mod helpers;
+pub mod journal;
+pub mod json_edit;
 pub mod msg;
+pub mod package_managers;
+pub mod plan;
 pub mod project_config;
+#[cfg(test)]
+pub mod test_repo;
+pub mod version;
@@ -1,5 +1,4 @@
 use colored::*;
-use std::process;
 
 pub struct Msg {
     pub(crate) text: String,
@@ -10,6 +9,11 @@ impl Msg {
         Msg { text: text.into() }
     }
 
+    pub fn plain(&mut self) -> &mut Self {
+        println!("{}", &self.text);
+        self
+    }
+
     pub fn info(&mut self) -> &mut Self {
         println!("{}", &self.text.cyan());
         self
@@ -24,22 +28,57 @@ impl Msg {
         println!("{}", &self.text.red());
         self
     }
-
-    pub fn exit(&mut self) {
-        process::exit(1);
-    }
 }
 
 pub const RELLR_FILE_IS_MISSING: &str = "The rellr configuration file is missing in the selected directory";
 pub const RELLR_FILE_IS_ALREADY_CREATED: &str = "The rellr configuration file has already been created";
 pub const RELLR_FILE_WAS_CREATED: &str = "The rellr configuration file was created successfully";
-pub const RELLR_INIT_HELP: &str = "To create a configuration file, run the command: `rellr init -n <your-project-name> -v <your-project-version>`";
+pub const RELLR_INIT_HELP: &str = "To create a configuration file, run the command: `rellr init <your-project-name> -v <your-project-version> -f <json|toml|cargo>`";
 pub const NEXT: &str = "Next version:";
 pub const RELEASE_ALREADY_EXISTS: &str = "The release already exists";
 pub const RELEASE_VERSION_NOT_SET: &str = "The release version has not yet been set";
+pub const INVALID_VERSION: &str = "Invalid version";
+pub const INVALID_CHANNEL: &str = "Invalid pre-release channel";
+pub const VERSION_NOT_A_PRERELEASE: &str = "Only a pre-release version can be promoted:";
+pub const VERSION_NOT_INCREASED: &str = "The next version must be greater than the current one:";
+pub const INVALID_CALVER_TOKEN: &str = "Unknown CalVer format part";
+pub const CALVER_PRERELEASE: &str = "Pre-release channels are only supported by the semver scheme";
+pub const VERSION_AUTO_NOT_RESOLVED: &str = "The `auto` update type has to be inferred from the commits first";
+pub const VERSION_NO_COMMITS: &str = "There are no commits since the last release tag";
+pub const VERSION_AUTO: &str = "bump inferred from:";
 pub const FEAT_WAS_CREATED: &str = "New feature was created successfully";
 pub const FIX_WAS_CREATED: &str = "New hotfix was created successfully";
 pub const RELEASE_COMPLETED_SUCCESSFULLY: &str = "Release completed successfully with version:";
 pub const RELEASE_FAILED: &str = "Release failed";
+pub const RELEASE_ROLLED_BACK: &str = "The release was rolled back, the repository is in its previous state";
+pub const RELEASE_ABORTED: &str = "Interrupted release was rolled back:";
+pub const RELEASE_IN_PROGRESS: &str = "An interrupted release was found. Run `rellr release --continue` to resume it or `rellr release --abort` to undo it";
+pub const RELEASE_NOT_IN_PROGRESS: &str = "There is no interrupted release to continue or abort";
+pub const CHANGELOG_SECTION_EXISTS: &str = "The changelog already has a section for the version, it was left untouched:";
+pub const UNKNOWN_CHANGELOG_PACKAGE: &str = "There is no changelog package named";
 pub const FILES_ARE_MISSING: &str = "The following files are missing:";
+pub const CARGO_VERSION_NOT_FOUND: &str = "No version to update, expected `[package].version` or `[workspace.package].version` in";
+pub const CARGO_UNKNOWN_MEMBER: &str = "Unknown workspace member in `members`:";
+pub const NPM_VERSION_NOT_FOUND: &str = "No version to update, expected `version` in";
+pub const NPM_UNKNOWN_MEMBER: &str = "Unknown workspace member in `members`:";
+pub const PYTHON_VERSION_NOT_FOUND: &str =
+    "No version to update, expected `[project].version` or `[tool.poetry].version` in pyproject.toml or `version` in setup.cfg in";
+pub const PYTHON_DUNDER_VERSION_NOT_FOUND: &str = "No `__version__` assignment in";
+pub const MAVEN_VERSION_NOT_FOUND: &str = "No version to update, expected `<project><version>` in";
+pub const GRADLE_VERSION_NOT_FOUND: &str = "No version to update, expected a `version` property in";
+pub const CARGO_REQUIREMENT_KEPT: &str = "Requirement left unchanged, only `=`, `^`, `~` and bare versions are rewritten:";
+pub const CARGO_INCONSISTENT_DEPENDENCIES: &str = "The versions of the workspace crates do not match the requirements of their dependents:";
+pub const PUBLISH_FAILED: &str = "Publishing failed:";
+pub const PUBLISH_TOKEN_NOT_SET: &str = "The environment variable with the registry token is not set:";
+pub const PUBLISHED_BEFORE_FAILURE: &str = "Published before the failure:";
+pub const PUBLISHED: &str = "Published";
+pub const CHECKOUT_CONFLICTS: &str = "Your local changes to the following files would be overwritten by checkout:";
+pub const CHECKOUT_CONFLICTS_HELP: &str = "Commit them or run the command again with `--stash`";
+pub const CHANGES_STASHED: &str = "Local changes were stashed, restore them with `git stash pop`:";
+pub const BARE_REPOSITORY: &str = "The repository has no working tree";
+pub const MERGE_FAST_FORWARD: &str = "Fast-Forward";
+pub const MERGE_NORMAL: &str = "Normal merge";
+pub const MERGE_UP_TO_DATE: &str = "The repository is up to date and no merge is required";
+pub const MERGE_CONFLICTS: &str = "Merge conflicts were detected! Resolve conflicts and commit manually";
+pub const MERGE_CONFLICTS_HELP: &str = "Then run `rellr release --continue` to finish the release, or `rellr release --abort` to undo it";
 
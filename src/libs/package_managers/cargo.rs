@@ -0,0 +1,558 @@
+use super::{parse_toml, set_string, token, CargoOptions, FileUpdate, PackageManagerTrait, PackageManagerType, PublishCommand, PublishOutcome};
+use crate::libs::error::RellrError;
+use crate::libs::helpers::to_path_str;
+use crate::libs::msg::{self, Msg};
+use semver::{Op, Version, VersionReq};
+use std::env;
+use std::fs::{metadata, read_to_string};
+use std::process::Command;
+use toml_edit::{Document, Item, TableLike};
+
+const MANIFEST: &str = "Cargo.toml";
+const LOCKFILE: &str = "Cargo.lock";
+const DEPENDENCY_KINDS: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+const WORKSPACE_DEPENDENCIES: &str = "workspace.dependencies";
+const CRATES_IO: &str = "crates-io";
+const DEFAULT_TOKEN_ENV: &str = "CARGO_REGISTRY_TOKEN";
+
+pub struct Cargo {
+    pub options: CargoOptions,
+}
+
+/// A manifest of the workspace, as read and as edited.
+struct Manifest {
+    path: String,
+    before: String,
+    document: Document,
+}
+
+impl Manifest {
+    fn read(path: String) -> Result<Self, RellrError> {
+        let before = read_to_string(&path)?;
+        let document = parse_toml(&path, &before)?;
+        Ok(Self { path, before, document })
+    }
+
+    fn name(&self) -> Option<String> {
+        self.document.get("package")?.get("name")?.as_str().map(String::from)
+    }
+
+    fn inherits_version(&self) -> bool {
+        self.document.get("package").and_then(|package| package.get("version")).is_some_and(inherits_version)
+    }
+
+    /// Version set in the manifest itself, `None` when it is inherited or missing.
+    fn version(&self) -> Option<String> {
+        self.document.get("package")?.get("version")?.as_str().map(String::from)
+    }
+
+    /// Directory of the crate.
+    fn directory(&self) -> String {
+        self.path.strip_suffix(MANIFEST).unwrap_or_default().trim_end_matches('/').to_string()
+    }
+
+    /// Whether `package.publish` allows publishing to `registry`, crates.io when `None`.
+    fn is_publishable(&self, registry: Option<&str>) -> bool {
+        let Some(publish) = self.document.get("package").and_then(|package| package.get("publish")) else {
+            return true;
+        };
+        match (publish.as_bool(), publish.as_array()) {
+            (Some(publish), _) => publish,
+            (_, Some(registries)) => registries.iter().any(|name| name.as_str() == Some(registry.unwrap_or(CRATES_IO))),
+            _ => true,
+        }
+    }
+
+    fn into_update(self) -> FileUpdate {
+        FileUpdate {
+            path: self.path,
+            before: self.before,
+            after: self.document.to_string(),
+        }
+    }
+}
+
+impl Cargo {
+    pub fn new(options: CargoOptions) -> Self {
+        Self { options }
+    }
+
+    fn path(&self, file: &str) -> String {
+        to_path_str(vec![self.options.path(), file])
+    }
+
+    /// The root manifest and the manifests of the workspace members.
+    fn manifests(&self) -> Result<(Manifest, Vec<Manifest>), RellrError> {
+        let root = Manifest::read(self.path(MANIFEST))?;
+        let mut members: Vec<Manifest> = vec![];
+        for manifest in self.member_manifests(&root.document)? {
+            members.push(Manifest::read(self.path(&manifest))?);
+        }
+        Ok((root, members))
+    }
+
+    /// Manifests of the members of `[workspace]`, relative to the package path.
+    fn member_manifests(&self, root: &Document) -> Result<Vec<String>, RellrError> {
+        let Some(workspace) = root.get("workspace") else {
+            return Ok(vec![]);
+        };
+        let list = |key: &str| -> Vec<String> {
+            workspace
+                .get(key)
+                .and_then(|item| item.as_array())
+                .map(|array| array.iter().filter_map(|value| value.as_str()).map(|path| to_path_str(vec![path])).collect())
+                .unwrap_or_default()
+        };
+        let excluded = list("exclude");
+        let prefix = match self.options.path() {
+            "" | "." => String::new(),
+            path => format!("{}/", to_path_str(vec![path]).trim_end_matches('/')),
+        };
+
+        let mut manifests: Vec<String> = vec![];
+        for member in list("members") {
+            let directories = glob::glob(&self.path(&member)).map_err(|e| RellrError::PackageManager(format!("{}: workspace.members: {}", self.path(MANIFEST), e)))?;
+            for directory in directories.flatten() {
+                let directory = to_path_str(vec![&directory.to_string_lossy()]);
+                let directory = directory.strip_prefix(&prefix).unwrap_or(&directory).to_string();
+                let manifest = to_path_str(vec![&directory, MANIFEST]);
+                if directory.is_empty() || excluded.contains(&directory) || manifests.contains(&manifest) || metadata(self.path(&manifest)).is_err() {
+                    continue;
+                }
+                manifests.push(manifest);
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// Names of the members whose own version is bumped.
+    fn selected_members(&self, members: &[Manifest]) -> Result<Vec<String>, RellrError> {
+        let names: Vec<String> = members.iter().filter_map(|member| member.name()).collect();
+        let Some(selected) = &self.options.members else {
+            return Ok(names);
+        };
+        match selected.iter().find(|name| !names.contains(name)) {
+            Some(name) => Err(RellrError::PackageManager(format!("{} {}", msg::CARGO_UNKNOWN_MEMBER, name))),
+            None => Ok(selected.clone()),
+        }
+    }
+
+    /// Names and directories of the crates to publish, each after the crates it depends on:
+    /// the root package and the selected members that `package.publish` allows for the registry.
+    fn publish_order(&self) -> Result<Vec<(String, String)>, RellrError> {
+        let (mut root, mut members) = self.manifests()?;
+        let selected = self.selected_members(&members)?;
+        let registry = self.options.registry.as_deref();
+
+        let mut crates: Vec<(String, String, Vec<String>)> = vec![];
+        for (index, manifest) in std::iter::once(&mut root).chain(members.iter_mut()).enumerate() {
+            let Some(name) = manifest.name().filter(|name| index == 0 || selected.contains(name)) else {
+                continue;
+            };
+            if !manifest.is_publishable(registry) {
+                continue;
+            }
+            // Development dependencies are not needed to publish a crate.
+            let mut dependencies: Vec<String> = vec![];
+            for_each_dependency(&mut manifest.document, |kind, name, dependency| {
+                let is_internal = dependency.contains_key("path") || dependency.get("workspace").and_then(|workspace| workspace.as_bool()) == Some(true);
+                if is_internal && kind != "dev-dependencies" && kind != WORKSPACE_DEPENDENCIES {
+                    dependencies.push(name.to_string());
+                }
+            });
+            crates.push((name, manifest.directory(), dependencies));
+        }
+
+        let mut order: Vec<(String, String)> = vec![];
+        while order.len() < crates.len() {
+            let is_ordered = |name: &str| order.iter().any(|(ordered, _)| ordered == name);
+            let is_pending = |name: &str| crates.iter().any(|(pending, _, _)| pending == name) && !is_ordered(name);
+            let next = crates
+                .iter()
+                .find(|(name, _, dependencies)| !is_ordered(name) && !dependencies.iter().any(|dependency| dependency != name && is_pending(dependency)));
+            match next {
+                Some((name, directory, _)) => order.push((name.clone(), directory.clone())),
+                // A dependency cycle, which cargo reports when publishing.
+                None => {
+                    let rest: Vec<(String, String)> = crates.iter().filter(|(name, _, _)| !is_ordered(name)).map(|(name, directory, _)| (name.clone(), directory.clone())).collect();
+                    order.extend(rest);
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// A `cargo publish --dry-run` of all crates, then a `cargo publish` of each crate. Cargo before 1.90
+    /// cannot package several crates at once, so each crate then gets its own dry run right before it is published.
+    fn commands(&self, crates: &[String]) -> Vec<PublishCommand> {
+        if crates.is_empty() {
+            return vec![];
+        }
+        if self.publishes_workspaces() {
+            let mut commands = vec![self.command(crates, true)];
+            commands.extend(crates.iter().map(|name| self.command(std::slice::from_ref(name), false)));
+            return commands;
+        }
+        crates.iter().flat_map(|name| [self.command(std::slice::from_ref(name), true), self.command(std::slice::from_ref(name), false)]).collect()
+    }
+
+    /// Whether `cargo publish` accepts several packages, which cargo supports since 1.90.
+    fn publishes_workspaces(&self) -> bool {
+        let output = Command::new(program()).arg("--version").output();
+        let version = output.ok().and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            stdout.split_whitespace().nth(1).and_then(|version| Version::parse(version).ok())
+        });
+        version.is_some_and(|version| version >= Version::new(1, 90, 0))
+    }
+
+    fn command(&self, crates: &[String], dry_run: bool) -> PublishCommand {
+        let mut args: Vec<String> = vec!["publish".into()];
+        if dry_run {
+            args.push("--dry-run".into());
+        }
+        for name in crates {
+            args.extend(["-p".into(), name.clone()]);
+        }
+        if let Some(registry) = &self.options.registry {
+            args.extend(["--registry".into(), registry.clone()]);
+        }
+        if self.options.allow_dirty() {
+            args.push("--allow-dirty".into());
+        }
+        PublishCommand {
+            program: program(),
+            args,
+            cwd: self.options.path().into(),
+        }
+    }
+
+    /// The token of `token_env`, passed to cargo in the variable it reads for the registry.
+    fn token_envs(&self) -> Result<Vec<(String, String)>, RellrError> {
+        let Some(token) = token(&self.options.token_env)? else {
+            return Ok(vec![]);
+        };
+        let name = match &self.options.registry {
+            Some(registry) => format!("CARGO_REGISTRIES_{}_TOKEN", registry.to_uppercase().replace('-', "_")),
+            None => DEFAULT_TOKEN_ENV.into(),
+        };
+        Ok(vec![(name, token)])
+    }
+}
+
+impl PackageManagerTrait for Cargo {
+    fn root(&self) -> &str {
+        self.options.path()
+    }
+
+    /// `Cargo.toml`, the manifests of the workspace members and, when it is committed, `Cargo.lock`.
+    fn files(&self) -> Vec<String> {
+        let mut files = vec![MANIFEST.to_string()];
+        if let Some(root) = read_to_string(self.path(MANIFEST)).ok().and_then(|data| data.parse::<Document>().ok()) {
+            files.extend(self.member_manifests(&root).unwrap_or_default());
+        }
+        if metadata(self.path(LOCKFILE)).is_ok() {
+            files.push(LOCKFILE.into());
+        }
+        files
+    }
+
+    /// Sets `[workspace.package].version`, the version of the root package and of the selected members,
+    /// and rewrites the requirements of the internal dependencies on the bumped crates.
+    /// Fails when a requirement of an internal dependency is no longer met.
+    fn version_updates(&self, _project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let (mut root, mut members) = self.manifests()?;
+        let selected = self.selected_members(&members)?;
+
+        // `Item::get_mut` would insert the missing tables, so the lookups go through `TableLike`.
+        let workspace_version = root
+            .document
+            .get_mut("workspace")
+            .and_then(Item::as_table_like_mut)
+            .and_then(|workspace| workspace.get_mut("package"))
+            .and_then(Item::as_table_like_mut)
+            .and_then(|package| package.get_mut("version"));
+        let workspace_updated = workspace_version.is_some_and(|item| set_string(item, version));
+
+        let mut bumped: Vec<String> = vec![];
+        for (index, manifest) in std::iter::once(&mut root).chain(members.iter_mut()).enumerate() {
+            let Some(name) = manifest.name() else {
+                continue;
+            };
+            let updated = match manifest.document.get_mut("package").and_then(Item::as_table_like_mut).and_then(|package| package.get_mut("version")) {
+                Some(item) if inherits_version(item) => workspace_updated,
+                Some(item) if index == 0 || selected.contains(&name) => set_string(item, version),
+                _ => false,
+            };
+            if updated && !bumped.contains(&name) {
+                bumped.push(name);
+            }
+        }
+        if !workspace_updated && bumped.is_empty() {
+            return Err(RellrError::PackageManager(format!("{} {}", msg::CARGO_VERSION_NOT_FOUND, root.path)));
+        }
+
+        for manifest in std::iter::once(&mut root).chain(members.iter_mut()) {
+            for_each_dependency(&mut manifest.document, |_, name, dependency| {
+                if dependency.contains_key("path") && bumped.iter().any(|bumped| bumped == name) {
+                    if let Some(item) = dependency.get_mut("version") {
+                        let requirement = item.as_str().unwrap_or_default().to_string();
+                        match bump_requirement(&requirement, version) {
+                            Some(bumped) => {
+                                set_string(item, &bumped);
+                            }
+                            None if item.is_str() => {
+                                Msg::new(&format!("{} {} = \"{}\" ({})", msg::CARGO_REQUIREMENT_KEPT, name, requirement, manifest.path)).warn();
+                            }
+                            None => {}
+                        }
+                    }
+                }
+            });
+        }
+        check_dependencies(&mut root, &mut members)?;
+
+        let mut updates: Vec<FileUpdate> = std::iter::once(root).chain(members).map(Manifest::into_update).collect();
+        let lockfile_path = self.path(LOCKFILE);
+        if let Ok(before) = read_to_string(&lockfile_path) {
+            let mut lockfile = parse_toml(&lockfile_path, &before)?;
+            update_lockfile(&mut lockfile, &bumped, version);
+            updates.push(FileUpdate {
+                path: lockfile_path,
+                before,
+                after: lockfile.to_string(),
+            });
+        }
+        Ok(updates)
+    }
+
+    fn publish_commands(&self, _version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+        let crates: Vec<String> = self.publish_order()?.into_iter().map(|(name, _)| name).collect();
+        Ok(self.commands(&crates))
+    }
+
+    /// Checks the crates with dry runs and publishes them one by one in dependency order.
+    /// When a crate fails, the error lists the crates that were already published.
+    fn publish(&self, _version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+
+        let crates = self.publish_order()?;
+        let envs = self.token_envs()?;
+        let names: Vec<String> = crates.iter().map(|(name, _)| name.clone()).collect();
+        let mut crates = crates.into_iter();
+
+        let mut outcomes: Vec<PublishOutcome> = vec![];
+        for command in self.commands(&names) {
+            if let Err(error) = command.run(&envs) {
+                let published: Vec<String> = outcomes.iter().filter_map(|outcome| outcome.name.clone()).collect();
+                return Err(match published.is_empty() {
+                    true => error,
+                    false => RellrError::PackageManager(format!("{}\n{} {}", error, msg::PUBLISHED_BEFORE_FAILURE, published.join(", "))),
+                });
+            }
+            if command.args.iter().any(|arg| arg == "--dry-run") {
+                continue;
+            }
+            let Some((name, directory)) = crates.next() else {
+                break;
+            };
+            outcomes.push(PublishOutcome {
+                package_manager: PackageManagerType::Cargo,
+                name: Some(name),
+                path: directory,
+                published: true,
+            });
+        }
+        Ok(outcomes)
+    }
+}
+
+/// Cargo sets `CARGO` for the commands it runs; it also allows to substitute cargo in tests.
+fn program() -> String {
+    env::var("CARGO").unwrap_or("cargo".into())
+}
+
+/// Whether a `version` key is `{ workspace = true }` or `version.workspace = true`.
+fn inherits_version(item: &Item) -> bool {
+    item.get("workspace").and_then(|workspace| workspace.as_bool()).unwrap_or(false)
+}
+
+/// Requirement on `version` with the operator of `requirement`, e.g. `^1.2` becomes `^1.3.0`.
+/// `None` when `requirement` is not a single `=`, `^`, `~` or bare comparator, e.g. `>=1.2, <2`.
+fn bump_requirement(requirement: &str, version: &str) -> Option<String> {
+    let parsed = VersionReq::parse(requirement).ok()?;
+    let [comparator] = parsed.comparators.as_slice() else {
+        return None;
+    };
+    let operator = match comparator.op {
+        Op::Exact => "=",
+        Op::Caret if requirement.trim_start().starts_with('^') => "^",
+        Op::Caret => "",
+        Op::Tilde => "~",
+        _ => return None,
+    };
+    Some(format!("{}{}", operator, version))
+}
+
+/// Calls `f` with the kind, the crate name and the table of every dependency given as a table, in `[dependencies]`,
+/// `[dev-dependencies]`, `[build-dependencies]`, their `[target.*]` variants and `[workspace.dependencies]`.
+fn for_each_dependency(document: &mut Document, mut f: impl FnMut(&str, &str, &mut dyn TableLike)) {
+    let mut tables: Vec<(String, &mut dyn TableLike)> = vec![];
+    for (key, item) in document.as_table_mut().iter_mut() {
+        match key.get() {
+            "target" => {
+                let targets = item.as_table_like_mut().into_iter().flat_map(|targets| targets.iter_mut());
+                for (_, target) in targets {
+                    let kinds = target.as_table_like_mut().into_iter().flat_map(|target| target.iter_mut());
+                    tables.extend(
+                        kinds
+                            .filter(|(kind, _)| DEPENDENCY_KINDS.contains(&kind.get()))
+                            .filter_map(|(kind, table)| Some((kind.get().to_string(), table.as_table_like_mut()?))),
+                    );
+                }
+            }
+            "workspace" => tables.extend(item.get_mut("dependencies").and_then(|table| table.as_table_like_mut()).map(|table| (WORKSPACE_DEPENDENCIES.to_string(), table))),
+            kind if DEPENDENCY_KINDS.contains(&kind) => tables.extend(item.as_table_like_mut().map(|table| (kind.to_string(), table))),
+            _ => {}
+        }
+    }
+
+    for (kind, table) in tables {
+        for (key, item) in table.iter_mut() {
+            if let Some(dependency) = item.as_table_like_mut() {
+                let name = dependency.get("package").and_then(|package| package.as_str()).unwrap_or(key.get()).to_string();
+                f(&kind, &name, dependency);
+            }
+        }
+    }
+}
+
+/// Checks that the version requirement of every internal dependency is met by the crate it points to.
+fn check_dependencies(root: &mut Manifest, members: &mut [Manifest]) -> Result<(), RellrError> {
+    let workspace_version = root
+        .document
+        .get("workspace")
+        .and_then(|workspace| workspace.get("package"))
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str())
+        .map(String::from);
+    let versions: Vec<(String, String)> = std::iter::once(&*root)
+        .chain(members.iter())
+        .filter_map(|manifest| {
+            let version = match manifest.inherits_version() {
+                true => workspace_version.clone(),
+                false => manifest.version(),
+            };
+            Some((manifest.name()?, version?))
+        })
+        .collect();
+
+    let mut errors: Vec<String> = vec![];
+    for manifest in std::iter::once(root).chain(members.iter_mut()) {
+        let path = manifest.path.clone();
+        for_each_dependency(&mut manifest.document, |_, name, dependency| {
+            let requirement = dependency.get("version").and_then(|requirement| requirement.as_str());
+            let version = versions.iter().find(|(crate_name, _)| crate_name == name).map(|(_, version)| version);
+            let (Some(requirement), Some(version), true) = (requirement, version, dependency.contains_key("path")) else {
+                return;
+            };
+            let matches = match (VersionReq::parse(requirement), Version::parse(version)) {
+                (Ok(requirement), Ok(version)) => requirement.matches(&version),
+                _ => false,
+            };
+            if !matches {
+                errors.push(format!("{}: {} {} does not match {}", path, name, version, requirement));
+            }
+        });
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(RellrError::PackageManager(format!("{}\n{}", msg::CARGO_INCONSISTENT_DEPENDENCIES, errors.join("\n")))),
+    }
+}
+
+/// Sets the version of the local crates of the lockfile, the ones without a `source`.
+fn update_lockfile(lockfile: &mut Document, crates: &[String], version: &str) {
+    let Some(packages) = lockfile.get_mut("package").and_then(|packages| packages.as_array_of_tables_mut()) else {
+        return;
+    };
+    for package in packages.iter_mut() {
+        let is_own_crate = package.get("source").is_none() && package.get("name").and_then(|name| name.as_str()).is_some_and(|name| crates.iter().any(|own| own == name));
+        if let Some(item) = package.get_mut("version").filter(|_| is_own_crate) {
+            set_string(item, version);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::test_repo::TestRepo;
+
+    #[test]
+    fn bump_requirement_keeps_the_operator() {
+        assert_eq!(bump_requirement("1.2", "1.3.0").as_deref(), Some("1.3.0"));
+        assert_eq!(bump_requirement("^1.2", "1.3.0").as_deref(), Some("^1.3.0"));
+        assert_eq!(bump_requirement("~1.2.3", "1.3.0").as_deref(), Some("~1.3.0"));
+        assert_eq!(bump_requirement("=1.2.3", "1.3.0").as_deref(), Some("=1.3.0"));
+    }
+
+    #[test]
+    fn bump_requirement_skips_ranges() {
+        assert_eq!(bump_requirement(">=1.2, <2", "1.3.0"), None);
+        assert_eq!(bump_requirement(">=1.2", "1.3.0"), None);
+        assert_eq!(bump_requirement("1.*", "1.3.0"), None);
+        assert_eq!(bump_requirement("not a requirement", "1.3.0"), None);
+    }
+
+    #[test]
+    fn version_updates_edit_the_manifests_and_the_lockfile() {
+        let repo = TestRepo::new("cargo-version-updates");
+        repo.write(MANIFEST, "# The workspace\n[workspace]\nmembers = [\"a\", \"b\"]\n\n[workspace.package]\nversion = \"0.1.0\" # shared\n");
+        repo.write("a/Cargo.toml", "[package]\nname = \"a\"\nversion.workspace = true\n");
+        repo.write(
+            "b/Cargo.toml",
+            "[package]\nname = \"b\"\nversion = \"0.1.0\"\n\n[dependencies]\na = { path = \"../a\", version = \"^0.1.0\" } # local\nserde = \"0.1.0\"\n",
+        );
+        repo.write(
+            LOCKFILE,
+            "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"0.1.0\"\n\n[[package]]\nname = \"b\"\nversion = \"0.1.0\"\ndependencies = [\"a\", \"serde\"]\n\n[[package]]\nname = \"serde\"\nversion = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+
+        let updates = Cargo::new(CargoOptions::default()).version_updates("demo", "0.2.0").unwrap();
+        let after: Vec<(&str, &str)> = updates.iter().filter(|update| update.is_changed()).map(|update| (update.path.as_str(), update.after.as_str())).collect();
+        assert_eq!(
+            after,
+            [
+                (MANIFEST, "# The workspace\n[workspace]\nmembers = [\"a\", \"b\"]\n\n[workspace.package]\nversion = \"0.2.0\" # shared\n"),
+                ("b/Cargo.toml", "[package]\nname = \"b\"\nversion = \"0.2.0\"\n\n[dependencies]\na = { path = \"../a\", version = \"^0.2.0\" } # local\nserde = \"0.1.0\"\n"),
+                (
+                    LOCKFILE,
+                    "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"0.2.0\"\n\n[[package]]\nname = \"b\"\nversion = \"0.2.0\"\ndependencies = [\"a\", \"serde\"]\n\n[[package]]\nname = \"serde\"\nversion = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn version_updates_only_bump_the_selected_members() {
+        let repo = TestRepo::new("cargo-selected-members");
+        repo.write(MANIFEST, "[workspace]\nmembers = [\"a\", \"b\"]\n");
+        repo.write("a/Cargo.toml", "[package]\nname = \"a\"\nversion = \"0.1.0\"\n");
+        repo.write("b/Cargo.toml", "[package]\nname = \"b\"\nversion = \"0.1.0\"\n");
+        let cargo = Cargo::new(CargoOptions {
+            members: Some(vec!["b".into()]),
+            ..CargoOptions::default()
+        });
+
+        let changed: Vec<String> = cargo.version_updates("demo", "0.2.0").unwrap().into_iter().filter(FileUpdate::is_changed).map(|update| update.path).collect();
+        assert_eq!(changed, ["b/Cargo.toml"]);
+        assert_eq!(cargo.publish_order().unwrap(), [("b".to_string(), "b".to_string())]);
+    }
+}
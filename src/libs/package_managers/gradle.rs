@@ -0,0 +1,117 @@
+use super::{FileUpdate, GradleOptions, PackageManagerTrait, PackageManagerType, PublishCommand, PublishOutcome};
+use crate::libs::error::RellrError;
+use crate::libs::helpers::{to_abs_path, to_path_str};
+use crate::libs::msg;
+use regex::Regex;
+use std::fs::{metadata, read_to_string};
+
+const PROPERTIES: &str = "gradle.properties";
+
+#[cfg(windows)]
+const WRAPPER: &str = "gradlew.bat";
+#[cfg(not(windows))]
+const WRAPPER: &str = "gradlew";
+
+#[cfg(windows)]
+const GRADLE: &str = "gradle.bat";
+#[cfg(not(windows))]
+const GRADLE: &str = "gradle";
+
+pub struct Gradle {
+    pub options: GradleOptions,
+}
+
+impl Gradle {
+    pub fn new(options: GradleOptions) -> Self {
+        Self { options }
+    }
+
+    fn path(&self, file: &str) -> String {
+        to_path_str(vec![self.options.path(), file])
+    }
+
+    /// The Gradle wrapper of the project when there is one, `gradle` otherwise.
+    fn program(&self) -> String {
+        match metadata(self.path(WRAPPER)).is_ok() {
+            true => to_abs_path(&self.path(WRAPPER)),
+            false => GRADLE.into(),
+        }
+    }
+}
+
+impl PackageManagerTrait for Gradle {
+    fn root(&self) -> &str {
+        self.options.path()
+    }
+
+    fn files(&self) -> Vec<String> {
+        vec![PROPERTIES.into()]
+    }
+
+    /// Sets the `version` property of `gradle.properties`, keeping the separator and the other lines.
+    fn version_updates(&self, _project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let path = self.path(PROPERTIES);
+        let before = read_to_string(&path)?;
+        let after = set_version(&before, version).ok_or(RellrError::PackageManager(format!("{} {}", msg::GRADLE_VERSION_NOT_FOUND, path)))?;
+        Ok(vec![FileUpdate { path, before, after }])
+    }
+
+    fn publish_commands(&self, _version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        Ok(match self.options.publish() {
+            true => vec![PublishCommand {
+                program: self.program(),
+                args: vec!["publish".into()],
+                cwd: self.options.path().into(),
+            }],
+            false => vec![],
+        })
+    }
+
+    /// Runs the `publish` task in the package folder.
+    fn publish(&self, version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+
+        for command in self.publish_commands(version)? {
+            command.run(&[])?;
+        }
+
+        Ok(vec![PublishOutcome {
+            package_manager: PackageManagerType::Gradle,
+            name: None,
+            path: self.options.path().into(),
+            published: true,
+        }])
+    }
+}
+
+/// Replaces the value of the first `version` property, keeping its quotes, `None` when there is none.
+fn set_version(data: &str, version: &str) -> Option<String> {
+    let re = Regex::new(r#"(?m)^([ \t]*version[ \t]*[=:][ \t]*)("?)[^"\r\n]*("?)"#).expect("valid regex");
+    re.is_match(data)
+        .then(|| re.replace(data, |captures: &regex::Captures| format!("{}{}{}{}", &captures[1], &captures[2], version, &captures[3])).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_version_replaces_the_value() {
+        assert_eq!(set_version("group=com.example\nversion=1.0.0\n", "1.1.0").as_deref(), Some("group=com.example\nversion=1.1.0\n"));
+        assert_eq!(set_version("  version : 1.0.0\r\nname=demo\r\n", "1.1.0").as_deref(), Some("  version : 1.1.0\r\nname=demo\r\n"));
+        assert_eq!(set_version("version=\"1.0.0\"\n", "1.1.0").as_deref(), Some("version=\"1.1.0\"\n"));
+    }
+
+    #[test]
+    fn set_version_skips_comments_and_other_keys() {
+        let data = "# version=0.0.1\nversionCode=3\napp.version=0.0.2\nversion=1.0.0\n";
+        assert_eq!(set_version(data, "1.1.0").as_deref(), Some("# version=0.0.1\nversionCode=3\napp.version=0.0.2\nversion=1.1.0\n"));
+    }
+
+    #[test]
+    fn set_version_needs_the_property() {
+        assert_eq!(set_version("# version=1.0.0\nversionName=1.0.0\n", "1.1.0"), None);
+    }
+}
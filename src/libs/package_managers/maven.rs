@@ -0,0 +1,311 @@
+use super::{FileUpdate, MavenOptions, PackageManagerTrait, PackageManagerType, PublishCommand, PublishOutcome};
+use crate::libs::error::RellrError;
+use crate::libs::helpers::to_path_str;
+use crate::libs::msg;
+use std::fs::read_to_string;
+use std::ops::Range;
+
+const POM: &str = "pom.xml";
+
+#[cfg(windows)]
+const MVN: &str = "mvn.cmd";
+#[cfg(not(windows))]
+const MVN: &str = "mvn";
+
+pub struct Maven {
+    pub options: MavenOptions,
+}
+
+/// A `pom.xml` of the build, as read and as edited.
+struct Pom {
+    path: String,
+    before: String,
+    after: String,
+}
+
+impl Pom {
+    fn read(path: String) -> Result<Self, RellrError> {
+        let before = read_to_string(&path)?;
+        Ok(Self {
+            path,
+            after: before.clone(),
+            before,
+        })
+    }
+
+    /// Text of the element at `path`, e.g. `project/parent/version`.
+    fn get(&self, path: &str) -> Option<String> {
+        let elements = elements(&self.after).ok()?;
+        let (_, range) = elements.into_iter().find(|(element, _)| element == path)?;
+        Some(self.after[range].trim().to_string())
+    }
+
+    /// Replaces the text of the element at `path`, keeping the whitespace around it and the rest of the file as it is.
+    fn set(&mut self, path: &str, text: &str) -> Result<bool, RellrError> {
+        let elements = elements(&self.after).map_err(|e| RellrError::PackageManager(format!("{}: {}", self.path, e)))?;
+        let Some((_, range)) = elements.into_iter().find(|(element, _)| element == path) else {
+            return Ok(false);
+        };
+        let value = &self.after[range.clone()];
+        let start = range.start + value.len() - value.trim_start().len();
+        let end = range.end - (value.len() - value.trim_end().len());
+        self.after.replace_range(start..end.max(start), text);
+        Ok(true)
+    }
+
+    fn modules(&self) -> Vec<String> {
+        elements(&self.after)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(element, _)| element == "project/modules/module")
+            .map(|(_, range)| self.after[range].trim().to_string())
+            .collect()
+    }
+
+    /// Group and artifact of the project; the group is inherited from the parent when it is not set.
+    fn coordinates(&self) -> (Option<String>, Option<String>) {
+        (self.get("project/groupId").or(self.get("project/parent/groupId")), self.get("project/artifactId"))
+    }
+
+    /// Sets the version of the project. When it is a property reference such as `${revision}`,
+    /// the property is set instead.
+    fn set_version(&mut self, version: &str) -> Result<bool, RellrError> {
+        match self.get("project/version") {
+            Some(current) if current.starts_with("${") && current.ends_with('}') => {
+                let property = &current[2..current.len() - 1];
+                self.set(&format!("project/properties/{}", property), version)
+            }
+            Some(_) => self.set("project/version", version),
+            None => Ok(false),
+        }
+    }
+
+    fn into_update(self) -> FileUpdate {
+        FileUpdate {
+            path: self.path,
+            before: self.before,
+            after: self.after,
+        }
+    }
+}
+
+impl Maven {
+    pub fn new(options: MavenOptions) -> Self {
+        Self { options }
+    }
+
+    fn path(&self, file: &str) -> String {
+        to_path_str(vec![self.options.path(), file])
+    }
+
+    /// The root `pom.xml`, then the `pom.xml` of the modules, recursively; paths are relative to the package path.
+    fn pom_files(&self) -> Result<Vec<String>, RellrError> {
+        let mut files = vec![POM.to_string()];
+        let mut index = 0;
+        while index < files.len() {
+            let pom = Pom::read(self.path(&files[index]))?;
+            let directory = files[index].strip_suffix(POM).unwrap_or_default().to_string();
+            for module in pom.modules() {
+                let module = to_path_str(vec![&format!("{}{}", directory, module)]);
+                let file = match module.ends_with(".xml") {
+                    true => module,
+                    false => to_path_str(vec![&module, POM]),
+                };
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+            index += 1;
+        }
+        Ok(files)
+    }
+}
+
+impl PackageManagerTrait for Maven {
+    fn root(&self) -> &str {
+        self.options.path()
+    }
+
+    /// The root `pom.xml` and the `pom.xml` of the modules.
+    fn files(&self) -> Vec<String> {
+        self.pom_files().unwrap_or(vec![POM.into()])
+    }
+
+    /// Sets the version of the root project and of the modules that have their own version,
+    /// and the `<parent><version>` of the modules whose parent is part of the build.
+    fn version_updates(&self, _project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let mut poms: Vec<Pom> = vec![];
+        for file in self.pom_files()? {
+            poms.push(Pom::read(self.path(&file))?);
+        }
+
+        let projects: Vec<(Option<String>, Option<String>)> = poms.iter().map(Pom::coordinates).collect();
+        let root = &mut poms[0];
+        if !root.set_version(version)? {
+            return Err(RellrError::PackageManager(format!("{} {}", msg::MAVEN_VERSION_NOT_FOUND, root.path)));
+        }
+        for pom in poms.iter_mut().skip(1) {
+            pom.set_version(version)?;
+            let parent = (pom.get("project/parent/groupId"), pom.get("project/parent/artifactId"));
+            let is_internal = projects.iter().any(|(group, artifact)| artifact.is_some() && *artifact == parent.1 && (group.is_none() || parent.0.is_none() || *group == parent.0));
+            // A reference such as `${revision}` follows the property of the parent.
+            let is_reference = pom.get("project/parent/version").is_some_and(|version| version.starts_with("${"));
+            if is_internal && !is_reference {
+                pom.set("project/parent/version", version)?;
+            }
+        }
+        Ok(poms.into_iter().map(Pom::into_update).collect())
+    }
+
+    fn publish_commands(&self, _version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        Ok(match self.options.publish() {
+            true => vec![PublishCommand {
+                program: MVN.into(),
+                args: vec!["deploy".into()],
+                cwd: self.options.path().into(),
+            }],
+            false => vec![],
+        })
+    }
+
+    /// Runs `mvn deploy` in the package folder.
+    fn publish(&self, version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+
+        for command in self.publish_commands(version)? {
+            command.run(&[])?;
+        }
+
+        Ok(vec![PublishOutcome {
+            package_manager: PackageManagerType::Maven,
+            name: Pom::read(self.path(POM)).ok().and_then(|pom| pom.get("project/artifactId")),
+            path: self.options.path().into(),
+            published: true,
+        }])
+    }
+}
+
+/// Elements that only contain text, with their path from the root element, e.g. `project/version`,
+/// and the range of their text. Comments, CDATA sections, processing instructions and declarations are skipped.
+fn elements(xml: &str) -> Result<Vec<(String, Range<usize>)>, String> {
+    let mut elements: Vec<(String, Range<usize>)> = vec![];
+    // Open elements: name, end of the start tag and whether a child element was found.
+    let mut stack: Vec<(String, usize, bool)> = vec![];
+    let mut position = 0;
+    while let Some(offset) = xml[position..].find('<') {
+        let start = position + offset;
+        let rest = &xml[start..];
+        let skip_to = |terminator: &str| rest.find(terminator).map(|end| start + end + terminator.len()).ok_or(format!("unterminated markup at byte {}", start));
+        let tag_end = || tag_end(rest).map(|end| start + end).ok_or(format!("unterminated markup at byte {}", start));
+        if rest.starts_with("<!--") {
+            position = skip_to("-->")?;
+        } else if rest.starts_with("<![CDATA[") {
+            position = skip_to("]]>")?;
+        } else if rest.starts_with("<?") {
+            position = skip_to("?>")?;
+        } else if rest.starts_with("<!") {
+            position = skip_to(">")?;
+        } else if let Some(name) = rest.strip_prefix("</") {
+            let end = tag_end()?;
+            let name = name[..end - start - 3].trim();
+            let (open, text_start, has_children) = stack.pop().ok_or(format!("unexpected </{}>", name))?;
+            if open != name {
+                return Err(format!("expected </{}>, found </{}>", open, name));
+            }
+            if !has_children {
+                let path: Vec<&str> = stack.iter().map(|(name, _, _)| name.as_str()).chain(std::iter::once(name)).collect();
+                elements.push((path.join("/"), text_start..start));
+            }
+            position = end;
+        } else {
+            let end = tag_end()?;
+            let tag = &xml[start + 1..end - 1];
+            let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default().to_string();
+            if let Some(parent) = stack.last_mut() {
+                parent.2 = true;
+            }
+            if !tag.ends_with('/') {
+                stack.push((name, end, false));
+            }
+            position = end;
+        }
+    }
+    Ok(elements)
+}
+
+/// Length of the tag at the start of `markup`, up to its closing `>` outside quoted attribute values.
+fn tag_end(markup: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            (Some(open), c) if c == open => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pom(xml: &str) -> Pom {
+        Pom {
+            path: POM.into(),
+            before: xml.into(),
+            after: xml.into(),
+        }
+    }
+
+    fn paths(xml: &str) -> Vec<String> {
+        elements(xml).unwrap().into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn set_keeps_the_whitespace_around_the_value() {
+        let mut pom = pom("<project>\n  <version>\n    1.0.0\n  </version>\n</project>\n");
+        assert!(pom.set_version("1.1.0").unwrap());
+        assert_eq!(pom.after, "<project>\n  <version>\n    1.1.0\n  </version>\n</project>\n");
+    }
+
+    #[test]
+    fn elements_skip_comments_and_cdata() {
+        let xml = "<?xml version=\"1.0\"?>\n<!DOCTYPE project>\n<project>\n  <!-- <version>0.0.1</version> -->\n  <description><![CDATA[<version>0.0.2</version>]]></description>\n  <version>1.0.0</version>\n</project>\n";
+        assert_eq!(paths(xml), ["project/description", "project/version"]);
+        assert_eq!(pom(xml).get("project/version").as_deref(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn elements_skip_empty_elements() {
+        let xml = "<project>\n  <version/>\n  <name />\n  <modules>\n    <module>core</module>\n  </modules>\n</project>\n";
+        assert_eq!(paths(xml), ["project/modules/module"]);
+        let mut pom = pom(xml);
+        assert_eq!(pom.get("project/version"), None);
+        assert!(!pom.set_version("1.1.0").unwrap());
+    }
+
+    #[test]
+    fn set_version_sets_the_project_property() {
+        let xml = "<project>\n  <version>${revision}</version>\n  <properties>\n    <revision>1.0.0</revision>\n  </properties>\n  <profiles>\n    <profile>\n      <properties>\n        <revision>0.0.0-dev</revision>\n      </properties>\n    </profile>\n  </profiles>\n</project>\n";
+        let mut pom = pom(xml);
+        assert!(pom.set_version("1.1.0").unwrap());
+        assert_eq!(pom.after, xml.replace("<revision>1.0.0</revision>", "<revision>1.1.0</revision>"));
+    }
+
+    #[test]
+    fn elements_skip_quoted_attribute_values() {
+        let xml = "<project>\n  <configuration combine.self=\"a>b\" name='<c>'>\n    <version>0.0.1</version>\n  </configuration>\n  <version>1.0.0</version>\n</project >\n";
+        assert_eq!(paths(xml), ["project/configuration/version", "project/version"]);
+        assert_eq!(pom(xml).get("project/version").as_deref(), Some("1.0.0"));
+        assert_eq!(elements("<project a=\"1>"), Err("unterminated markup at byte 0".into()));
+    }
+
+    #[test]
+    fn elements_report_mismatched_tags() {
+        assert_eq!(elements("<project><version>1</project>"), Err("expected </version>, found </project>".into()));
+    }
+}
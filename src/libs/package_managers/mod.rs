@@ -0,0 +1,485 @@
+mod cargo;
+mod gradle;
+mod maven;
+mod npm;
+mod python;
+
+pub use cargo::Cargo;
+pub use gradle::Gradle;
+pub use maven::Maven;
+pub use npm::{Npm, NpmAccess, NpmClient};
+pub use python::Python;
+
+use super::error::RellrError;
+use super::helpers::to_path_str;
+use super::msg;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::env;
+use std::fmt;
+use std::process::Command;
+use toml_edit::{Document, Item, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManagerType {
+    Cargo,
+    Npm,
+    Python,
+    Maven,
+    Gradle,
+}
+
+/// Adds the accessors shared by the options of every package manager.
+macro_rules! common_options {
+    ($($options:ty),*) => {
+        $(impl $options {
+            pub fn path(&self) -> &str {
+                self.path.as_deref().unwrap_or_default()
+            }
+
+            pub fn publish(&self) -> bool {
+                self.publish.unwrap_or(false)
+            }
+        })*
+    };
+}
+
+common_options!(CargoOptions, NpmOptions, PythonOptions, MavenOptions, GradleOptions);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CargoOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+    /// Workspace members whose own `version` is bumped and that are published; all members when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<String>>,
+    /// Registry to publish to instead of crates.io.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Name of the environment variable that holds the registry token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// Publish even when the working directory has uncommitted changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_dirty: Option<bool>,
+}
+
+impl CargoOptions {
+    pub fn allow_dirty(&self) -> bool {
+        self.allow_dirty.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NpmOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+    /// Workspace members whose own `version` is bumped; all members when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<String>>,
+    /// Registry to publish to instead of the default one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Name of the environment variable that holds the registry token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// Publish even when the working directory has uncommitted changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_dirty: Option<bool>,
+    /// Client that publishes the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<NpmClient>,
+    /// Access level of a scoped package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access: Option<NpmAccess>,
+}
+
+impl NpmOptions {
+    pub fn allow_dirty(&self) -> bool {
+        self.allow_dirty.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PythonOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+    /// Repository URL to upload to instead of PyPI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Name of the environment variable that holds the registry token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// Python module that defines `__version__`, relative to `path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_file: Option<String>,
+    /// Command that builds the distributions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// Command that uploads the distributions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_command: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MavenOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradleOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+}
+
+/// Value of the `token_env` environment variable, `None` when no variable is configured.
+fn token(token_env: &Option<String>) -> Result<Option<String>, RellrError> {
+    match token_env {
+        Some(name) => env::var(name).map(Some).map_err(|_| RellrError::PackageManager(format!("{} {}", msg::PUBLISH_TOKEN_NOT_SET, name))),
+        None => Ok(None),
+    }
+}
+
+/// A single entry of `package_managers`, tagged by its `type`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PackageManager {
+    Cargo(CargoOptions),
+    Npm(NpmOptions),
+    Python(PythonOptions),
+    Maven(MavenOptions),
+    Gradle(GradleOptions),
+}
+
+impl PackageManager {
+    /// A package manager of the given type with the default options.
+    pub fn new(package_manager_type: PackageManagerType) -> Self {
+        match package_manager_type {
+            PackageManagerType::Cargo => PackageManager::Cargo(CargoOptions::default()),
+            PackageManagerType::Npm => PackageManager::Npm(NpmOptions::default()),
+            PackageManagerType::Python => PackageManager::Python(PythonOptions::default()),
+            PackageManagerType::Maven => PackageManager::Maven(MavenOptions::default()),
+            PackageManagerType::Gradle => PackageManager::Gradle(GradleOptions::default()),
+        }
+    }
+
+    pub fn package_manager_type(&self) -> PackageManagerType {
+        match self {
+            PackageManager::Cargo(_) => PackageManagerType::Cargo,
+            PackageManager::Npm(_) => PackageManagerType::Npm,
+            PackageManager::Python(_) => PackageManagerType::Python,
+            PackageManager::Maven(_) => PackageManagerType::Maven,
+            PackageManager::Gradle(_) => PackageManagerType::Gradle,
+        }
+    }
+
+    /// Whether no option is set, in which case the entry is written as its bare type.
+    pub fn has_default_options(&self) -> bool {
+        *self == PackageManager::new(self.package_manager_type())
+    }
+
+    pub fn adapter(&self) -> Box<dyn PackageManagerTrait> {
+        match self {
+            PackageManager::Cargo(options) => Box::new(Cargo::new(options.clone())),
+            PackageManager::Npm(options) => Box::new(Npm::new(options.clone())),
+            PackageManager::Python(options) => Box::new(Python::new(options.clone())),
+            PackageManager::Maven(options) => Box::new(Maven::new(options.clone())),
+            PackageManager::Gradle(options) => Box::new(Gradle::new(options.clone())),
+        }
+    }
+}
+
+/// List of package managers. Several entries of the same type are allowed,
+/// e.g. two npm packages in different folders.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "PackageManagersRepr", into = "Vec<PackageManagerEntry>")]
+pub struct PackageManagers(pub Vec<PackageManager>);
+
+impl PackageManagers {
+    pub fn iter(&self) -> impl Iterator<Item = &PackageManager> {
+        self.0.iter()
+    }
+
+    pub fn adapters(&self) -> Vec<Box<dyn PackageManagerTrait>> {
+        self.iter().map(|package_manager| package_manager.adapter()).collect()
+    }
+}
+
+/// Entry as written in the config: a bare type name (`"cargo"`) or a tagged table.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PackageManagerEntry {
+    Bare(PackageManagerType),
+    Tagged(PackageManager),
+}
+
+/// Picks the form from the shape of the value, so that the error of a bad entry is the one of that form.
+impl<'de> Deserialize<'de> for PackageManagerEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = PackageManagerEntry;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a package manager type or a table with a `type`")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                PackageManagerType::deserialize(value.into_deserializer()).map(PackageManagerEntry::Bare)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                PackageManager::deserialize(MapAccessDeserializer::new(map)).map(PackageManagerEntry::Tagged)
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
+}
+
+/// The legacy map form: `{ "cargo": { ... }, "npm": { ... } }`.
+#[derive(Deserialize)]
+struct LegacyPackageManagers {
+    cargo: Option<CargoOptions>,
+    npm: Option<NpmOptions>,
+}
+
+enum PackageManagersRepr {
+    List(Vec<PackageManagerEntry>),
+    Map(Box<LegacyPackageManagers>),
+}
+
+/// A list is read as the list form; only an object falls back to the legacy map.
+impl<'de> Deserialize<'de> for PackageManagersRepr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReprVisitor;
+
+        impl<'de> Visitor<'de> for ReprVisitor {
+            type Value = PackageManagersRepr;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of package managers")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(PackageManagersRepr::List)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                LegacyPackageManagers::deserialize(MapAccessDeserializer::new(map)).map(|map| PackageManagersRepr::Map(Box::new(map)))
+            }
+        }
+
+        deserializer.deserialize_any(ReprVisitor)
+    }
+}
+
+impl From<PackageManagersRepr> for PackageManagers {
+    fn from(repr: PackageManagersRepr) -> Self {
+        let package_managers = match repr {
+            PackageManagersRepr::List(entries) => entries
+                .into_iter()
+                .map(|entry| match entry {
+                    PackageManagerEntry::Bare(package_manager_type) => PackageManager::new(package_manager_type),
+                    PackageManagerEntry::Tagged(package_manager) => package_manager,
+                })
+                .collect(),
+            PackageManagersRepr::Map(map) => [map.cargo.map(PackageManager::Cargo), map.npm.map(PackageManager::Npm)].into_iter().flatten().collect(),
+        };
+        Self(package_managers)
+    }
+}
+
+impl From<PackageManagers> for Vec<PackageManagerEntry> {
+    fn from(package_managers: PackageManagers) -> Self {
+        package_managers
+            .0
+            .into_iter()
+            .map(|package_manager| match package_manager.has_default_options() {
+                true => PackageManagerEntry::Bare(package_manager.package_manager_type()),
+                false => PackageManagerEntry::Tagged(package_manager),
+            })
+            .collect()
+    }
+}
+
+/// New contents of a file whose version has to be changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileUpdate {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl FileUpdate {
+    pub fn is_changed(&self) -> bool {
+        self.before != self.after
+    }
+}
+
+/// Result of publishing one package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishOutcome {
+    pub package_manager: PackageManagerType,
+    /// Name of the published package, e.g. the crate of a workspace member.
+    pub name: Option<String>,
+    pub path: String,
+    pub published: bool,
+}
+
+/// External command run to publish a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+}
+
+impl PublishCommand {
+    /// Runs the command in its `cwd` with the additional environment variables.
+    /// Fails when the command cannot be started or exits with a non-zero status.
+    pub fn run(&self, envs: &[(String, String)]) -> Result<(), RellrError> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(envs.iter().map(|(key, value)| (key, value)));
+        if !self.cwd.is_empty() {
+            command.current_dir(&self.cwd);
+        }
+        let status = command.status().map_err(|e| RellrError::PackageManager(format!("{} `{}`: {}", msg::PUBLISH_FAILED, self, e)))?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(RellrError::PackageManager(format!("{} `{}` exited with {}", msg::PUBLISH_FAILED, self, status))),
+        }
+    }
+}
+
+impl fmt::Display for PublishCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", [vec![self.program.clone()], self.args.clone()].concat().join(" "))
+    }
+}
+
+pub trait PackageManagerTrait {
+    /// Folder of the package, relative to the project root.
+    fn root(&self) -> &str;
+    fn files(&self) -> Vec<String>;
+    fn publish(&self, version: &str) -> Result<Vec<PublishOutcome>, RellrError>;
+
+    /// Commands that [`publish`](PackageManagerTrait::publish) runs, used to preview a release.
+    fn publish_commands(&self, _version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        Ok(vec![])
+    }
+
+    fn paths(&self) -> Vec<String> {
+        self.files().iter().map(|file| to_path_str(vec![self.root(), file])).collect()
+    }
+
+    fn version_updates(&self, project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError>;
+}
+
+fn parse_toml(path: &str, data: &str) -> Result<Document, RellrError> {
+    data.parse::<Document>().map_err(|e| RellrError::PackageManager(format!("{}: {}", path, e.to_string().trim_end())))
+}
+
+/// Replaces a string value, keeping the whitespace and comments around it.
+fn set_string(item: &mut Item, string: &str) -> bool {
+    let Some(value) = item.as_value_mut().filter(|value| value.is_str()) else {
+        return false;
+    };
+    let decor = value.decor().clone();
+    *value = Value::from(string);
+    *value.decor_mut() = decor;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_list_form() {
+        let package_managers: PackageManagers = serde_json::from_str(r#"["cargo", { "type": "npm", "path": "web", "publish": true }]"#).unwrap();
+        assert_eq!(
+            package_managers.0,
+            [
+                PackageManager::new(PackageManagerType::Cargo),
+                PackageManager::Npm(NpmOptions {
+                    path: Some("web".into()),
+                    publish: Some(true),
+                    ..NpmOptions::default()
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_legacy_map() {
+        let package_managers: PackageManagers = serde_json::from_str(r#"{ "npm": { "path": "web" }, "cargo": { "publish": true } }"#).unwrap();
+        assert_eq!(
+            package_managers.0,
+            [
+                PackageManager::Cargo(CargoOptions {
+                    publish: Some(true),
+                    ..CargoOptions::default()
+                }),
+                PackageManager::Npm(NpmOptions {
+                    path: Some("web".into()),
+                    ..NpmOptions::default()
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_error_of_a_bad_entry() {
+        let error = serde_json::from_str::<PackageManagers>("[\n  \"cargo\",\n  { \"type\": \"nmp\" }\n]").unwrap_err();
+        assert!(error.to_string().starts_with("unknown variant `nmp`, expected one of `cargo`"), "{}", error);
+        assert_eq!(error.line(), 3);
+
+        let error = serde_json::from_str::<PackageManagers>("[\"carg\"]").unwrap_err();
+        assert!(error.to_string().starts_with("unknown variant `carg`"), "{}", error);
+
+        let error = serde_json::from_str::<PackageManagers>(r#"{ "cargo": { "publish": "yes" } }"#).unwrap_err();
+        assert_eq!(error.to_string(), "invalid type: string \"yes\", expected a boolean at line 1 column 29");
+    }
+
+    #[test]
+    fn rejects_the_options_of_another_package_manager() {
+        let error = serde_json::from_str::<PackageManagers>(r#"[{ "type": "maven", "client": "pnpm" }]"#).unwrap_err();
+        assert!(error.to_string().starts_with("unknown field `client`, expected `path` or `publish`"), "{}", error);
+
+        let error = serde_json::from_str::<PackageManagers>(r#"{ "cargo": { "access": "public" } }"#).unwrap_err();
+        assert!(error.to_string().starts_with("unknown field `access`"), "{}", error);
+    }
+
+    #[test]
+    fn writes_entries_without_options_as_their_type() {
+        let package_managers = PackageManagers(vec![
+            PackageManager::new(PackageManagerType::Gradle),
+            PackageManager::Python(PythonOptions {
+                version_file: Some("demo/__init__.py".into()),
+                ..PythonOptions::default()
+            }),
+        ]);
+        let json = serde_json::to_string(&package_managers).unwrap();
+        assert_eq!(json, r#"["gradle",{"type":"python","version_file":"demo/__init__.py"}]"#);
+    }
+}
@@ -0,0 +1,525 @@
+use super::{token, FileUpdate, NpmOptions, PackageManagerTrait, PackageManagerType, PublishCommand, PublishOutcome};
+use crate::libs::error::RellrError;
+use crate::libs::git::Git;
+use crate::libs::helpers::to_path_str;
+use crate::libs::json_edit::string_span;
+use crate::libs::msg;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{metadata, read_to_string};
+
+const MANIFEST: &str = "package.json";
+const LOCKFILES: [&str; 2] = ["package-lock.json", "npm-shrinkwrap.json"];
+const PNPM_WORKSPACE: &str = "pnpm-workspace.yaml";
+
+/// Client that runs the publish command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NpmClient {
+    #[default]
+    Npm,
+    Pnpm,
+    /// Yarn 2 or later, which publishes with `yarn npm publish`.
+    Yarn,
+}
+
+impl NpmClient {
+    #[cfg(windows)]
+    fn program(&self) -> &'static str {
+        match self {
+            NpmClient::Npm => "npm.cmd",
+            NpmClient::Pnpm => "pnpm.cmd",
+            NpmClient::Yarn => "yarn.cmd",
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn program(&self) -> &'static str {
+        match self {
+            NpmClient::Npm => "npm",
+            NpmClient::Pnpm => "pnpm",
+            NpmClient::Yarn => "yarn",
+        }
+    }
+}
+
+/// Access level of a scoped package, passed as `--access`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NpmAccess {
+    Public,
+    Restricted,
+}
+
+impl NpmAccess {
+    fn as_str(&self) -> &'static str {
+        match self {
+            NpmAccess::Public => "public",
+            NpmAccess::Restricted => "restricted",
+        }
+    }
+}
+
+pub struct Npm {
+    pub options: NpmOptions,
+}
+
+/// A JSON file of the package, as read and as edited.
+/// Values are replaced in the text itself, which keeps the indentation and the layout of the file.
+struct JsonFile {
+    path: String,
+    before: String,
+    after: String,
+    value: Value,
+}
+
+impl JsonFile {
+    fn read(path: String) -> Result<Self, RellrError> {
+        let before = read_to_string(&path)?;
+        let value = serde_json::from_str(&before).map_err(|e| RellrError::PackageManager(format!("{}: {}", path, e)))?;
+        Ok(Self {
+            path,
+            after: before.clone(),
+            before,
+            value,
+        })
+    }
+
+    /// Sets the `version` string of the object at `keys`, when it has one.
+    fn set_version(&mut self, keys: &[&str], version: &str) -> bool {
+        let keys = [keys, &["version"]].concat();
+        let Some(span) = string_span(&self.after, &keys) else {
+            return false;
+        };
+        self.after.replace_range(span, &Value::String(version.into()).to_string());
+        true
+    }
+
+    fn name(&self) -> Option<String> {
+        self.value.get("name")?.as_str().map(String::from)
+    }
+
+    /// Directory of the file, relative to the package path `prefix`.
+    fn directory(&self, prefix: &str) -> String {
+        let directory = self.path.rsplit_once('/').map_or("", |(directory, _)| directory);
+        match prefix {
+            "" => directory.to_string(),
+            prefix => directory.strip_prefix(prefix).map_or(directory, |directory| directory.trim_start_matches('/')).to_string(),
+        }
+    }
+
+    fn into_update(self) -> FileUpdate {
+        FileUpdate {
+            path: self.path,
+            before: self.before,
+            after: self.after,
+        }
+    }
+}
+
+impl Npm {
+    pub fn new(options: NpmOptions) -> Self {
+        Self { options }
+    }
+
+    fn path(&self, file: &str) -> String {
+        to_path_str(vec![self.options.path(), file])
+    }
+
+    /// The root `package.json` and the `package.json` of the workspace members.
+    fn manifests(&self) -> Result<(JsonFile, Vec<JsonFile>), RellrError> {
+        let root = JsonFile::read(self.path(MANIFEST))?;
+        let mut members: Vec<JsonFile> = vec![];
+        for manifest in self.member_manifests(&root.value)? {
+            members.push(JsonFile::read(self.path(&manifest))?);
+        }
+        Ok((root, members))
+    }
+
+    /// Manifests of the workspace members, relative to the package path.
+    /// The members are listed in `workspaces` of `package.json` for npm and yarn, or in `pnpm-workspace.yaml`.
+    fn member_manifests(&self, root: &Value) -> Result<Vec<String>, RellrError> {
+        let workspaces = root.get("workspaces").map(|workspaces| workspaces.get("packages").unwrap_or(workspaces));
+        let patterns: Vec<String> = match workspaces.and_then(|workspaces| workspaces.as_array()) {
+            Some(patterns) => patterns.iter().filter_map(|pattern| pattern.as_str()).map(String::from).collect(),
+            None => read_to_string(self.path(PNPM_WORKSPACE)).map(|data| pnpm_workspace_packages(&data)).unwrap_or_default(),
+        };
+        let (excluded, included): (Vec<String>, Vec<String>) = patterns.into_iter().map(|pattern| to_path_str(vec![&pattern])).partition(|pattern| pattern.starts_with('!'));
+        let excluded: Vec<glob::Pattern> = excluded.iter().filter_map(|pattern| glob::Pattern::new(&pattern[1..]).ok()).collect();
+        let prefix = match self.options.path() {
+            "" | "." => String::new(),
+            path => format!("{}/", to_path_str(vec![path]).trim_end_matches('/')),
+        };
+
+        let mut manifests: Vec<String> = vec![];
+        for pattern in included {
+            let directories = glob::glob(&self.path(&pattern)).map_err(|e| RellrError::PackageManager(format!("{}: workspaces: {}", self.path(MANIFEST), e)))?;
+            for directory in directories.flatten() {
+                let directory = to_path_str(vec![&directory.to_string_lossy()]);
+                let directory = directory.strip_prefix(&prefix).unwrap_or(&directory).trim_end_matches('/').to_string();
+                let manifest = to_path_str(vec![&directory, MANIFEST]);
+                if directory.is_empty() || excluded.iter().any(|pattern| pattern.matches(&directory)) || manifests.contains(&manifest) || metadata(self.path(&manifest)).is_err() {
+                    continue;
+                }
+                manifests.push(manifest);
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// Names of the members whose version is bumped.
+    fn selected_members(&self, members: &[JsonFile]) -> Result<Vec<String>, RellrError> {
+        let names: Vec<String> = members.iter().filter_map(|member| member.name()).collect();
+        let Some(selected) = &self.options.members else {
+            return Ok(names);
+        };
+        match selected.iter().find(|name| !names.contains(name)) {
+            Some(name) => Err(RellrError::PackageManager(format!("{} {}", msg::NPM_UNKNOWN_MEMBER, name))),
+            None => Ok(selected.clone()),
+        }
+    }
+
+    /// The committed lockfiles, relative to the package path.
+    fn lockfiles(&self) -> Vec<&'static str> {
+        LOCKFILES.into_iter().filter(|lockfile| Git::is_tracked(&self.path(lockfile))).collect()
+    }
+
+    fn client(&self) -> NpmClient {
+        self.options.client.unwrap_or_default()
+    }
+
+    /// Name of the package in `package.json`.
+    fn name(&self) -> Option<String> {
+        JsonFile::read(self.path(MANIFEST)).ok()?.name()
+    }
+
+    /// A dry run of the publish, except with yarn which has none, then the publish itself.
+    fn commands(&self, version: &str) -> Vec<PublishCommand> {
+        match self.client() {
+            NpmClient::Yarn => vec![self.command(version, false)],
+            NpmClient::Npm | NpmClient::Pnpm => vec![self.command(version, true), self.command(version, false)],
+        }
+    }
+
+    fn command(&self, version: &str, dry_run: bool) -> PublishCommand {
+        let client = self.client();
+        let mut args: Vec<String> = match client {
+            NpmClient::Yarn => vec!["npm".into(), "publish".into()],
+            NpmClient::Npm | NpmClient::Pnpm => vec!["publish".into()],
+        };
+        if dry_run {
+            args.push("--dry-run".into());
+        }
+        if let Some(tag) = dist_tag(version) {
+            args.extend(["--tag".into(), tag]);
+        }
+        if let Some(access) = self.options.access {
+            args.extend(["--access".into(), access.as_str().into()]);
+        }
+        // Yarn reads the registry from `YARN_NPM_PUBLISH_REGISTRY`, see `envs`.
+        if let (Some(registry), NpmClient::Npm | NpmClient::Pnpm) = (&self.options.registry, client) {
+            args.extend(["--registry".into(), registry.clone()]);
+        }
+        // pnpm refuses to publish from a dirty working directory or from another branch than the main one.
+        if client == NpmClient::Pnpm && self.options.allow_dirty() {
+            args.push("--no-git-checks".into());
+        }
+        PublishCommand {
+            program: client.program().into(),
+            args,
+            cwd: self.options.path().into(),
+        }
+    }
+
+    /// The token of `token_env` in the variable read by the client, and the registry for yarn.
+    /// npm and pnpm only read `NODE_AUTH_TOKEN` when `.npmrc` refers to it, e.g. `//registry.npmjs.org/:_authToken=${NODE_AUTH_TOKEN}`.
+    fn envs(&self) -> Result<Vec<(String, String)>, RellrError> {
+        let client = self.client();
+        let mut envs: Vec<(String, String)> = vec![];
+        if let Some(token) = token(&self.options.token_env)? {
+            let name = match client {
+                NpmClient::Yarn => "YARN_NPM_AUTH_TOKEN",
+                NpmClient::Npm | NpmClient::Pnpm => "NODE_AUTH_TOKEN",
+            };
+            envs.push((name.into(), token));
+        }
+        if let (Some(registry), NpmClient::Yarn) = (&self.options.registry, client) {
+            envs.push(("YARN_NPM_PUBLISH_REGISTRY".into(), registry.clone()));
+        }
+        Ok(envs)
+    }
+}
+
+impl PackageManagerTrait for Npm {
+    fn root(&self) -> &str {
+        self.options.path()
+    }
+
+    /// `package.json`, the `package.json` of the workspace members and the committed lockfiles.
+    fn files(&self) -> Vec<String> {
+        let mut files = vec![MANIFEST.to_string()];
+        if let Ok(root) = JsonFile::read(self.path(MANIFEST)) {
+            files.extend(self.member_manifests(&root.value).unwrap_or_default());
+        }
+        files.extend(self.lockfiles().into_iter().map(String::from));
+        files
+    }
+
+    /// Sets the version of the root package and of the selected workspace members,
+    /// and the versions of the same packages in `package-lock.json`.
+    fn version_updates(&self, _project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let (mut root, mut members) = self.manifests()?;
+        let selected = self.selected_members(&members)?;
+        let prefix = match self.options.path() {
+            "" | "." => String::new(),
+            path => to_path_str(vec![path]).trim_end_matches('/').to_string(),
+        };
+
+        // Directories of the bumped packages, as keyed in `packages` of the lockfile.
+        let mut bumped: Vec<String> = vec![];
+        for (index, manifest) in std::iter::once(&mut root).chain(members.iter_mut()).enumerate() {
+            let is_selected = index == 0 || manifest.name().is_some_and(|name| selected.contains(&name));
+            if is_selected && manifest.set_version(&[], version) {
+                bumped.push(match index {
+                    0 => String::new(),
+                    _ => manifest.directory(&prefix),
+                });
+            }
+        }
+        if bumped.is_empty() {
+            return Err(RellrError::PackageManager(format!("{} {}", msg::NPM_VERSION_NOT_FOUND, root.path)));
+        }
+
+        let mut updates: Vec<FileUpdate> = std::iter::once(root).chain(members).map(JsonFile::into_update).collect();
+        for lockfile in self.lockfiles() {
+            let mut lockfile = JsonFile::read(self.path(lockfile))?;
+            if bumped.contains(&String::new()) {
+                lockfile.set_version(&[], version);
+            }
+            // `packages` exists since lockfile version 2 and has an entry for the root and each workspace member.
+            for directory in &bumped {
+                lockfile.set_version(&["packages", directory], version);
+            }
+            updates.push(lockfile.into_update());
+        }
+        Ok(updates)
+    }
+
+    fn publish_commands(&self, version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        Ok(match self.options.publish() {
+            true => self.commands(version),
+            false => vec![],
+        })
+    }
+
+    /// Publishes the package from its folder, after a dry run when the client supports it.
+    fn publish(&self, version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+
+        let envs = self.envs()?;
+        for command in self.commands(version) {
+            command.run(&envs)?;
+        }
+
+        Ok(vec![PublishOutcome {
+            package_manager: PackageManagerType::Npm,
+            name: self.name(),
+            path: self.options.path().into(),
+            published: true,
+        }])
+    }
+}
+
+/// Patterns of the `packages` list of `pnpm-workspace.yaml`.
+fn pnpm_workspace_packages(data: &str) -> Vec<String> {
+    let unquote = |pattern: &str| pattern.trim().trim_matches(['\'', '"']).to_string();
+    let mut packages: Vec<String> = vec![];
+    let mut in_packages = false;
+    for line in data.lines() {
+        let content = line.split(" #").next().unwrap_or_default().trim_end();
+        if content.trim().is_empty() || content.trim_start().starts_with('#') {
+            continue;
+        }
+        if !content.starts_with([' ', '\t', '-']) {
+            let value = content.strip_prefix("packages:").map(str::trim);
+            in_packages = value == Some("");
+            // A flow sequence on one line: `packages: [a, 'b']`.
+            if let Some(list) = value.and_then(|value| value.strip_prefix('[')).and_then(|value| value.strip_suffix(']')) {
+                packages.extend(list.split(',').map(unquote).filter(|pattern| !pattern.is_empty()));
+            }
+            continue;
+        }
+        if let Some(pattern) = content.trim_start().strip_prefix('-').filter(|_| in_packages) {
+            packages.push(unquote(pattern));
+        }
+    }
+    packages
+}
+
+/// Dist-tag of a pre-release, its channel: `1.3.0-beta.2` is published under `beta`.
+/// Other versions get npm's default tag, `latest`.
+fn dist_tag(version: &str) -> Option<String> {
+    let version = Version::parse(version).ok()?;
+    version.pre.as_str().split('.').next().filter(|channel| !channel.is_empty()).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::test_repo::TestRepo;
+
+    fn json_file(data: &str) -> JsonFile {
+        JsonFile {
+            path: MANIFEST.into(),
+            before: data.into(),
+            after: data.into(),
+            value: serde_json::from_str(data).unwrap(),
+        }
+    }
+
+    #[test]
+    fn set_version_skips_escaped_strings() {
+        let data = r#"{
+  "name": "a \"version\": \"0.0.1\" \\",
+  "description": "\"version\"",
+  "version": "1.0.0"
+}"#;
+        let mut file = json_file(data);
+        assert!(file.set_version(&[], "1.1.0"));
+        assert_eq!(file.after, data.replace("\"1.0.0\"", "\"1.1.0\""));
+    }
+
+    #[test]
+    fn set_version_skips_nested_version_keys() {
+        let data = r#"{
+  "name": "a",
+  "config": { "version": "0.0.1", "nested": [{ "version": "0.0.2" }] },
+  "version": "1.0.0"
+}"#;
+        let mut file = json_file(data);
+        assert!(file.set_version(&[], "1.1.0"));
+        assert_eq!(file.after, data.replace("\"1.0.0\"", "\"1.1.0\""));
+
+        let mut file = json_file(r#"{ "name": "a", "config": { "version": "0.0.1" } }"#);
+        assert!(!file.set_version(&[], "1.1.0"));
+    }
+
+    #[test]
+    fn set_version_of_the_lockfile_root_package() {
+        let data = r#"{
+  "name": "a",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "packages": {
+    "node_modules/b": { "version": "1.0.0" },
+    "": {
+      "name": "a",
+      "version": "1.0.0"
+    }
+  }
+}"#;
+        let mut file = json_file(data);
+        assert!(file.set_version(&[], "1.1.0"));
+        assert!(file.set_version(&["packages", ""], "1.1.0"));
+        let expected = data.replacen("\"version\": \"1.0.0\",", "\"version\": \"1.1.0\",", 1).replace("\"version\": \"1.0.0\"\n", "\"version\": \"1.1.0\"\n");
+        assert_eq!(file.after, expected);
+        assert!(file.after.contains(r#""node_modules/b": { "version": "1.0.0" }"#));
+    }
+
+    #[test]
+    fn pnpm_workspace_packages_forms() {
+        let data = "packages:\n  - 'packages/*'\n  - \"apps/*\" # the apps\n  - '!packages/internal'\ncatalog:\n  - skipped\n";
+        assert_eq!(pnpm_workspace_packages(data), ["packages/*", "apps/*", "!packages/internal"]);
+        assert_eq!(pnpm_workspace_packages("packages: [packages/*, '!packages/internal']\n"), ["packages/*", "!packages/internal"]);
+    }
+
+    #[test]
+    fn files_are_the_members_and_the_tracked_lockfiles() {
+        let repo = TestRepo::new("npm-files");
+        repo.write(MANIFEST, r#"{ "name": "root", "version": "1.0.0" }"#);
+        repo.write(PNPM_WORKSPACE, "packages:\n  - 'packages/*'\n  - '!packages/internal'\n");
+        repo.write("packages/a/package.json", r#"{ "name": "a", "version": "1.0.0" }"#);
+        repo.write("packages/internal/package.json", r#"{ "name": "internal", "version": "1.0.0" }"#);
+        repo.write("package-lock.json", "{}");
+        repo.write("npm-shrinkwrap.json", "{}");
+        repo.git(&["add", MANIFEST, "package-lock.json"]);
+
+        let npm = Npm::new(NpmOptions::default());
+        assert_eq!(npm.files(), [MANIFEST, "packages/a/package.json", "package-lock.json"]);
+    }
+
+    fn args(commands: &[PublishCommand]) -> Vec<String> {
+        commands.iter().map(|command| command.args.join(" ")).collect()
+    }
+
+    #[test]
+    fn dist_tag_is_the_channel_of_a_pre_release() {
+        assert_eq!(dist_tag("1.3.0-beta.2").as_deref(), Some("beta"));
+        assert_eq!(dist_tag("1.3.0-rc").as_deref(), Some("rc"));
+        assert_eq!(dist_tag("1.3.0"), None);
+    }
+
+    #[test]
+    fn publish_args_of_each_client() {
+        let npm = Npm::new(NpmOptions {
+            path: Some("web".into()),
+            access: Some(NpmAccess::Public),
+            registry: Some("https://npm.example.com".into()),
+            ..NpmOptions::default()
+        });
+        let commands = npm.commands("1.3.0-beta.2");
+        assert_eq!(
+            args(&commands),
+            [
+                "publish --dry-run --tag beta --access public --registry https://npm.example.com",
+                "publish --tag beta --access public --registry https://npm.example.com",
+            ]
+        );
+        assert!(commands.iter().all(|command| command.program == NpmClient::Npm.program() && command.cwd == "web"));
+
+        let pnpm = Npm::new(NpmOptions {
+            client: Some(NpmClient::Pnpm),
+            allow_dirty: Some(true),
+            ..NpmOptions::default()
+        });
+        assert_eq!(args(&pnpm.commands("1.3.0")), ["publish --dry-run --no-git-checks", "publish --no-git-checks"]);
+
+        let yarn = Npm::new(NpmOptions {
+            client: Some(NpmClient::Yarn),
+            registry: Some("https://npm.example.com".into()),
+            ..NpmOptions::default()
+        });
+        assert_eq!(args(&yarn.commands("1.3.0-rc.0")), ["npm publish --tag rc"]);
+    }
+
+    #[test]
+    fn publish_envs_of_each_client() {
+        std::env::set_var("RELLR_TEST_NPM_TOKEN", "secret");
+        let options = NpmOptions {
+            registry: Some("https://npm.example.com".into()),
+            token_env: Some("RELLR_TEST_NPM_TOKEN".into()),
+            ..NpmOptions::default()
+        };
+        let envs = |client: NpmClient| {
+            Npm::new(NpmOptions {
+                client: Some(client),
+                ..options.clone()
+            })
+            .envs()
+            .unwrap()
+        };
+        let pair = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(envs(NpmClient::Npm), [pair("NODE_AUTH_TOKEN", "secret")]);
+        assert_eq!(envs(NpmClient::Pnpm), [pair("NODE_AUTH_TOKEN", "secret")]);
+        assert_eq!(envs(NpmClient::Yarn), [pair("YARN_NPM_AUTH_TOKEN", "secret"), pair("YARN_NPM_PUBLISH_REGISTRY", "https://npm.example.com")]);
+
+        let missing = Npm::new(NpmOptions {
+            token_env: Some("RELLR_TEST_NPM_TOKEN_MISSING".into()),
+            ..NpmOptions::default()
+        });
+        assert!(matches!(missing.envs(), Err(RellrError::PackageManager(_))));
+    }
+}
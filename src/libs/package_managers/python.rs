@@ -0,0 +1,224 @@
+use super::{parse_toml, set_string, token, FileUpdate, PackageManagerTrait, PackageManagerType, PublishCommand, PublishOutcome, PythonOptions};
+use crate::libs::error::RellrError;
+use crate::libs::helpers::to_path_str;
+use crate::libs::msg;
+use regex::Regex;
+use std::fs::{metadata, read_to_string};
+use toml_edit::Item;
+
+const PYPROJECT: &str = "pyproject.toml";
+const SETUP_CFG: &str = "setup.cfg";
+const DEFAULT_BUILD_COMMAND: &str = "python -m build";
+const DEFAULT_UPLOAD_COMMAND: &str = "twine upload dist/*";
+
+pub struct Python {
+    pub options: PythonOptions,
+}
+
+impl Python {
+    pub fn new(options: PythonOptions) -> Self {
+        Self { options }
+    }
+
+    fn path(&self, file: &str) -> String {
+        to_path_str(vec![self.options.path(), file])
+    }
+
+    fn exists(&self, file: &str) -> bool {
+        metadata(self.path(file)).is_ok()
+    }
+
+    /// Name of the project in `pyproject.toml`, from `[project]` or `[tool.poetry]`.
+    fn name(&self) -> Option<String> {
+        let data = read_to_string(self.path(PYPROJECT)).ok()?;
+        let document = parse_toml(PYPROJECT, &data).ok()?;
+        let poetry = document.get("tool").and_then(|tool| tool.get("poetry"));
+        document.get("project").or(poetry)?.get("name")?.as_str().map(String::from)
+    }
+
+    /// The build command, then the upload command, both run in the package folder.
+    fn commands(&self) -> Vec<PublishCommand> {
+        let build = self.options.build_command.as_deref().unwrap_or(DEFAULT_BUILD_COMMAND);
+        let upload = self.options.upload_command.as_deref().unwrap_or(DEFAULT_UPLOAD_COMMAND);
+        [build, upload].into_iter().filter_map(|command| self.command(command)).collect()
+    }
+
+    /// Splits a command line on whitespace; it is not run through a shell.
+    fn command(&self, command: &str) -> Option<PublishCommand> {
+        let mut words = command.split_whitespace().map(String::from);
+        Some(PublishCommand {
+            program: words.next()?,
+            args: words.collect(),
+            cwd: self.options.path().into(),
+        })
+    }
+
+    /// The registry and the token in the variables read by twine.
+    fn envs(&self) -> Result<Vec<(String, String)>, RellrError> {
+        let mut envs: Vec<(String, String)> = vec![];
+        if let Some(registry) = &self.options.registry {
+            envs.push(("TWINE_REPOSITORY_URL".into(), registry.clone()));
+        }
+        if let Some(token) = token(&self.options.token_env)? {
+            envs.push(("TWINE_USERNAME".into(), "__token__".into()));
+            envs.push(("TWINE_PASSWORD".into(), token));
+        }
+        Ok(envs)
+    }
+}
+
+impl PackageManagerTrait for Python {
+    fn root(&self) -> &str {
+        self.options.path()
+    }
+
+    /// `pyproject.toml` and `setup.cfg` when they exist, and the module of `version_file`.
+    fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = [PYPROJECT, SETUP_CFG].into_iter().filter(|file| self.exists(file)).map(String::from).collect();
+        if let Some(version_file) = &self.options.version_file {
+            files.push(version_file.clone());
+        }
+        files
+    }
+
+    /// Sets `[project].version` and `[tool.poetry].version` in `pyproject.toml`, `version` in the `[metadata]`
+    /// of `setup.cfg` and `__version__` in the module of `version_file`. Versions read from elsewhere
+    /// at build time, e.g. `dynamic = ["version"]` or `version = attr: ...`, are left alone.
+    fn version_updates(&self, _project_name: &str, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let mut updates: Vec<FileUpdate> = vec![];
+
+        if self.exists(PYPROJECT) {
+            let path = self.path(PYPROJECT);
+            let before = read_to_string(&path)?;
+            let mut document = parse_toml(&path, &before)?;
+            let mut updated = false;
+            // `Item::get_mut` would insert the missing tables, so the lookups go through `TableLike`.
+            if let Some(item) = document.get_mut("project").and_then(Item::as_table_like_mut).and_then(|project| project.get_mut("version")) {
+                updated |= set_string(item, version);
+            }
+            let poetry = document.get_mut("tool").and_then(Item::as_table_like_mut).and_then(|tool| tool.get_mut("poetry"));
+            if let Some(item) = poetry.and_then(Item::as_table_like_mut).and_then(|poetry| poetry.get_mut("version")) {
+                updated |= set_string(item, version);
+            }
+            if updated {
+                updates.push(FileUpdate {
+                    path,
+                    before,
+                    after: document.to_string(),
+                });
+            }
+        }
+
+        if self.exists(SETUP_CFG) {
+            let path = self.path(SETUP_CFG);
+            let before = read_to_string(&path)?;
+            if let Some(after) = set_setup_cfg_version(&before, version) {
+                updates.push(FileUpdate { path, before, after });
+            }
+        }
+
+        if let Some(version_file) = &self.options.version_file {
+            let path = self.path(version_file);
+            let before = read_to_string(&path)?;
+            let after = set_dunder_version(&before, version).ok_or(RellrError::PackageManager(format!("{} {}", msg::PYTHON_DUNDER_VERSION_NOT_FOUND, path)))?;
+            updates.push(FileUpdate { path, before, after });
+        }
+
+        match updates.is_empty() {
+            true => Err(RellrError::PackageManager(format!("{} {}", msg::PYTHON_VERSION_NOT_FOUND, self.path("")))),
+            false => Ok(updates),
+        }
+    }
+
+    fn publish_commands(&self, _version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        Ok(match self.options.publish() {
+            true => self.commands(),
+            false => vec![],
+        })
+    }
+
+    /// Builds the distributions, then uploads them.
+    fn publish(&self, _version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        if !self.options.publish() {
+            return Ok(vec![]);
+        }
+
+        let envs = self.envs()?;
+        for command in self.commands() {
+            command.run(&envs)?;
+        }
+
+        Ok(vec![PublishOutcome {
+            package_manager: PackageManagerType::Python,
+            name: self.name(),
+            path: self.options.path().into(),
+            published: true,
+        }])
+    }
+}
+
+/// Replaces the static `version` of the `[metadata]` section, `None` when there is none.
+fn set_setup_cfg_version(contents: &str, version: &str) -> Option<String> {
+    let re = Regex::new(r"^(version\s*[=:]\s*)(\S.*?)\s*$").expect("valid regex");
+    let mut in_metadata = false;
+    let mut updated = false;
+    let mut lines: Vec<String> = vec![];
+    for line in contents.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content.starts_with('[') {
+            in_metadata = content.trim_end() == "[metadata]";
+        }
+        match re.captures(content).filter(|captures| in_metadata && !updated && !captures[2].starts_with("attr:") && !captures[2].starts_with("file:")) {
+            Some(captures) => {
+                lines.push(format!("{}{}{}", &captures[1], version, &line[content.len()..]));
+                updated = true;
+            }
+            None => lines.push(line.into()),
+        }
+    }
+    updated.then(|| lines.concat())
+}
+
+/// Replaces the string assigned to `__version__`, keeping its quotes; `None` when there is no assignment.
+fn set_dunder_version(contents: &str, version: &str) -> Option<String> {
+    let re = Regex::new(r#"(?m)^(__version__\s*(?::\s*str\s*)?=\s*)(["'])[^"'\n]*(["'])"#).expect("valid regex");
+    let captures = re.captures(contents)?;
+    let range = captures.get(0)?.range();
+    Some(format!("{}{}{}{}{}{}", &contents[..range.start], &captures[1], &captures[2], version, &captures[3], &contents[range.end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::test_repo::TestRepo;
+
+    #[test]
+    fn setup_cfg_version_in_metadata() {
+        let contents = "[options]\nversion = 0.0.1\n\n[metadata]\nname = demo\nversion = 1.0.0\r\n";
+        let expected = "[options]\nversion = 0.0.1\n\n[metadata]\nname = demo\nversion = 1.1.0\r\n";
+        assert_eq!(set_setup_cfg_version(contents, "1.1.0").as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn setup_cfg_version_read_at_build_time() {
+        assert_eq!(set_setup_cfg_version("[metadata]\nname = demo\nversion = attr: demo.__version__\n", "1.1.0"), None);
+        assert_eq!(set_setup_cfg_version("[metadata]\nversion = file: VERSION\n", "1.1.0"), None);
+    }
+
+    #[test]
+    fn dunder_version_keeps_its_quotes() {
+        assert_eq!(set_dunder_version("__version__ = '1.0.0'\n", "1.1.0").as_deref(), Some("__version__ = '1.1.0'\n"));
+        assert_eq!(set_dunder_version("\"\"\"Demo.\"\"\"\n__version__: str = \"1.0.0\"\n", "1.1.0").as_deref(), Some("\"\"\"Demo.\"\"\"\n__version__: str = \"1.1.0\"\n"));
+        assert_eq!(set_dunder_version("version = '1.0.0'\n", "1.1.0"), None);
+    }
+
+    #[test]
+    fn pyproject_version_leaves_other_tool_tables_alone() {
+        let repo = TestRepo::new("python-pyproject");
+        repo.write(PYPROJECT, "[project]\nname = \"demo\"\nversion = \"1.0.0\" # released\n\n[tool.black]\nline-length = 100\n");
+
+        let updates = Python::new(PythonOptions::default()).version_updates("demo", "1.1.0").unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].after, "[project]\nname = \"demo\"\nversion = \"1.1.0\" # released\n\n[tool.black]\nline-length = 100\n");
+    }
+}
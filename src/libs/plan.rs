@@ -0,0 +1,168 @@
+use super::helpers::diff_lines;
+use super::msg::Msg;
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlanFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// A single change that a command would make.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    InitRepository { main_branch: String },
+    CreateBranch { name: String, from: String },
+    RenameBranch { from: String, to: String },
+    Checkout { branch: String },
+    Merge {
+        from: String,
+        into: String,
+        strategy: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+    DeleteBranch { name: String },
+    WriteFile { path: String, diff: String },
+    Changelog { path: String, section: String },
+    Commit { message: String, files: Vec<String> },
+    Tag { name: String },
+    Publish { command: String, cwd: String },
+    Rollback { version: String },
+}
+
+impl Action {
+    /// Line that describes the action in the text output.
+    fn title(&self) -> String {
+        match self {
+            Action::InitRepository { main_branch } => format!("Initialize a git repository with branch {}", main_branch),
+            Action::CreateBranch { name, from } => format!("Create branch {} from {}", name, from),
+            Action::RenameBranch { from, to } => format!("Rename branch {} to {}", from, to),
+            Action::Checkout { branch } => format!("Checkout {}", branch),
+            Action::Merge { from, into, strategy, message } => match message {
+                Some(message) => format!("Merge {} into {} ({}) with commit \"{}\"", from, into, strategy, message),
+                None => format!("Merge {} into {} ({})", from, into, strategy),
+            },
+            Action::DeleteBranch { name } => format!("Delete branch {}", name),
+            Action::WriteFile { path, .. } => format!("Write {}", path),
+            Action::Changelog { path, .. } => format!("Update {}", path),
+            Action::Commit { message, files } => format!("Commit \"{}\" with {}", message, files.join(", ")),
+            Action::Tag { name } => format!("Tag {}", name),
+            Action::Publish { command, cwd } => format!("Run `{}` in {}", command, if cwd.is_empty() { "." } else { cwd }),
+            Action::Rollback { version } => format!("Roll back the interrupted release {}", version),
+        }
+    }
+}
+
+/// Actions collected by a dry run, in the order they would be executed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    pub actions: Vec<Action>,
+}
+
+impl Plan {
+    pub fn push(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Records a file write, skipping files whose contents would not change.
+    pub fn write_file(&mut self, path: &str, before: &str, after: &str) {
+        if before != after {
+            self.push(Action::WriteFile {
+                path: path.into(),
+                diff: diff_lines(before, after),
+            });
+        }
+    }
+
+    pub fn print(&self, format: PlanFormat) {
+        match format {
+            PlanFormat::Json => println!("{}", serde_json::to_string_pretty(self).unwrap_or_default()),
+            PlanFormat::Text => self.print_text(),
+        }
+    }
+
+    fn print_text(&self) {
+        Msg::new("Dry run: no files, refs or index will be changed").warn();
+        for (index, action) in self.actions.iter().enumerate() {
+            Msg::new(&format!("{}. {}", index + 1, action.title())).info();
+
+            match action {
+                Action::WriteFile { diff, .. } => {
+                    for line in diff.lines() {
+                        match line.chars().next() {
+                            Some('+') => Msg::new(line).info(),
+                            Some('-') => Msg::new(line).error(),
+                            _ => Msg::new(line).plain(),
+                        };
+                    }
+                }
+                Action::Changelog { section, .. } => {
+                    for line in section.lines() {
+                        Msg::new(line).plain();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn plan() -> Plan {
+        let mut plan = Plan::default();
+        plan.write_file("rellr.json", "{\n  \"current\": \"1.0.0\"\n}\n", "{\n  \"current\": \"1.1.0\"\n}\n");
+        plan.write_file("Cargo.toml", "version = \"1.0.0\"\n", "version = \"1.0.0\"\n");
+        plan.push(Action::Merge {
+            from: "release/1.1.0".into(),
+            into: "main".into(),
+            strategy: "fast-forward".into(),
+            message: None,
+        });
+        plan.push(Action::Publish {
+            command: "cargo publish".into(),
+            cwd: "".into(),
+        });
+        plan
+    }
+
+    #[test]
+    fn skips_files_that_do_not_change() {
+        let plan = plan();
+        let paths: Vec<&str> = plan
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::WriteFile { path, .. } => Some(path.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths, ["rellr.json"]);
+    }
+
+    #[test]
+    fn text_titles() {
+        let titles: Vec<String> = plan().actions.iter().map(Action::title).collect();
+        assert_eq!(titles, ["Write rellr.json", "Merge release/1.1.0 into main (fast-forward)", "Run `cargo publish` in ."]);
+    }
+
+    #[test]
+    fn json_shape() {
+        let value = serde_json::to_value(plan()).unwrap();
+        let expected = json!({
+            "actions": [
+                { "action": "write_file", "path": "rellr.json", "diff": "@@ -1,3 +1,3 @@\n {\n-  \"current\": \"1.0.0\"\n+  \"current\": \"1.1.0\"\n }\n" },
+                { "action": "merge", "from": "release/1.1.0", "into": "main", "strategy": "fast-forward" },
+                { "action": "publish", "command": "cargo publish", "cwd": "" },
+            ]
+        });
+        assert_eq!(value, expected);
+    }
+}
@@ -1,35 +1,11 @@
-use super::git::BranchType;
-use super::helpers::to_path_str;
+use super::changelog::ChangelogOptions;
+use super::config_file::ConfigFormat;
+use super::error::RellrError;
+use super::git::{BranchType, GitConfig};
 use super::msg;
-use crate::commands::init::InitArgs;
-use crate::commands::next::UpdateType;
-use crate::libs::helpers::to_abs_path;
-use crate::libs::msg::Msg;
+use super::package_managers::{FileUpdate, PackageManagers, PublishCommand, PublishOutcome};
+use super::version::{BumpConfig, UpdateType, VersionScheme};
 use serde::{Deserialize, Serialize};
-use serde_json::from_str;
-use std::env;
-use std::error::Error;
-use std::fs::{metadata, read_to_string, File};
-use std::path::Path;
-use std::process::Command;
-
-pub const PROJECT_CONFIG: &str = "rellr.json";
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PackageManager {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub path: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub publish: Option<bool>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PackageManagers {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    cargo: Option<PackageManager>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    npm: Option<PackageManager>,
-}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
@@ -41,63 +17,73 @@ pub struct ProjectConfig {
     pub prev: Option<String>,
     #[serde(skip)]
     pub branch_type: BranchType,
+    #[serde(skip)]
+    pub config_format: ConfigFormat,
     pub main_branch: String,
+    #[serde(default, skip_serializing_if = "GitConfig::is_default")]
+    pub git: GitConfig,
+    #[serde(default, skip_serializing_if = "VersionScheme::is_default")]
+    pub scheme: VersionScheme,
+    #[serde(default, skip_serializing_if = "BumpConfig::is_default")]
+    pub bump: BumpConfig,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub changelog: Option<String>,
+    pub changelog: Option<ChangelogOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package_managers: Option<PackageManagers>,
 }
 
 impl ProjectConfig {
-    pub fn new(init_args: InitArgs) -> Self {
+    pub fn new(name: &str, version: Option<String>, config_format: ConfigFormat) -> Self {
         Self {
-            name: init_args.name,
-            current: init_args.version.unwrap_or("0.0.0".to_string()),
+            name: name.into(),
+            current: version.unwrap_or("0.0.0".to_string()),
             next: None,
             prev: None,
             branch_type: BranchType::Release,
+            config_format,
             main_branch: "main".into(),
+            git: GitConfig::default(),
+            scheme: VersionScheme::default(),
+            bump: BumpConfig::default(),
             changelog: None,
             package_managers: None,
         }
     }
 
-    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
-        let file = File::create(PROJECT_CONFIG)?;
-        serde_json::to_writer_pretty(&file, &self)?;
-        Ok(())
+    pub fn save(&mut self) -> Result<(), RellrError> {
+        self.config_format.write(self)
     }
 
-    pub fn get() -> Result<Self, Box<dyn Error>> {
-        let data = read_to_string(PROJECT_CONFIG);
-        if data.is_err() {
-            Msg::new(msg::RELLR_FILE_IS_MISSING).error();
-            Msg::new(msg::RELLR_INIT_HELP).info().exit()
-        }
-        let data = data.unwrap();
-        let project_config: Self = from_str(&data).unwrap();
+    /// Contents of the configuration file before and after saving this configuration.
+    pub fn render(&self) -> Result<(String, String), RellrError> {
+        Ok((self.config_format.contents(), self.config_format.render(self)?))
+    }
+
+    pub fn get() -> Result<Self, RellrError> {
+        let config_format = ConfigFormat::discover().ok_or(RellrError::Config(format!("{}\n{}", msg::RELLR_FILE_IS_MISSING, msg::RELLR_INIT_HELP)))?;
+        let mut project_config: Self = config_format.read()?;
+        project_config.config_format = config_format;
         Ok(project_config)
     }
 
-    pub fn new_if_not_exist(init_args: InitArgs) -> Result<Self, Box<dyn Error>> {
-        if metadata(PROJECT_CONFIG).is_ok() {
-            Msg::new(msg::RELLR_FILE_IS_ALREADY_CREATED).warn().exit()
+    pub fn config_file_name(&self) -> &'static str {
+        self.config_format.file_name()
+    }
+
+    pub fn new_if_not_exist(name: &str, version: Option<String>, config_format: ConfigFormat) -> Result<Self, RellrError> {
+        if ConfigFormat::discover().is_some() {
+            return Err(RellrError::Config(msg::RELLR_FILE_IS_ALREADY_CREATED.into()));
         }
 
-        Ok(Self::new(init_args))
+        Ok(Self::new(name, version, config_format))
     }
 
-    pub fn up_version(&mut self, update_type: &UpdateType) -> Result<Self, Box<dyn Error>> {
-        let mut version_vec: Vec<u32> = self.current.split(".").filter_map(|s| s.parse().ok()).collect();
-        version_vec = match update_type {
-            UpdateType::Patch => Self::increment(version_vec, 2),
-            UpdateType::Minor => Self::increment(version_vec, 1),
-            UpdateType::Major => Self::increment(version_vec, 0),
-        };
-        let next = version_vec.iter().map(|&n| n.to_string()).collect::<Vec<String>>().join(".");
+    /// Sets `next` to the version following `current`; `channel` selects the pre-release channel.
+    pub fn up_version(&mut self, update_type: &UpdateType, channel: Option<&str>) -> Result<Self, RellrError> {
+        let next = self.scheme.adapter().bump(&self.current, update_type, channel)?;
 
         if self.next.clone().is_some_and(|n: String| n == next) {
-            return Err(msg::RELEASE_ALREADY_EXISTS.to_string().into());
+            return Err(RellrError::Version(msg::RELEASE_ALREADY_EXISTS.into()));
         }
 
         self.prev = self.next.clone();
@@ -105,159 +91,56 @@ impl ProjectConfig {
         Ok(self.to_owned())
     }
 
-    pub fn next_to_current(&mut self) -> Result<Self, Box<dyn Error>> {
-        if self.next.is_none() {
-            Msg::new(msg::RELEASE_VERSION_NOT_SET).error().exit()
-        }
-        self.current = self.next.clone().unwrap();
+    pub fn next_to_current(&mut self) -> Result<Self, RellrError> {
+        self.current = self.next_version()?;
         self.next = None;
         Ok(self.to_owned())
     }
 
-    pub fn paths(&mut self) -> Vec<String> {
-        let mut paths: Vec<String> = vec![];
-        if self.package_managers.is_none() {
-            return paths;
-        }
-
-        paths.append(&mut Cargo::new(&self).paths());
-        paths.append(&mut Npm::new(&self).paths());
-        paths
-    }
-
-    pub fn publish(&mut self) -> Result<(), Box<dyn Error>> {
-        if self.package_managers.is_none() {
-            return Ok(());
-        }
-
-        Cargo::new(&self).publish()?;
-        Npm::new(&self).publish()?;
-
-        Ok(())
+    /// The version being released; fails when `next` has not been run yet.
+    pub fn next_version(&self) -> Result<String, RellrError> {
+        self.next.clone().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))
     }
 
-    fn increment(mut version_vec: Vec<u32>, index: usize) -> Vec<u32> {
-        if let Some(value) = version_vec.get_mut(index) {
-            *value += 1;
-
-            for i in (index + 1)..version_vec.len() {
-                version_vec[i] = 0;
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = vec![];
+        if let Some(package_managers) = &self.package_managers {
+            for package_manager in package_managers.adapters() {
+                paths.append(&mut package_manager.paths());
             }
         }
-        version_vec
-    }
-}
-
-trait PackageManagerTrait {
-    fn new(project_config: &ProjectConfig) -> Self;
-    fn files(&self) -> Vec<String>;
-    fn paths(&self) -> Vec<String>;
-    fn publish(&self) -> Result<(), Box<dyn Error>>;
-
-    fn pm_config(package_manager: &Option<PackageManager>) -> PackageManagerConfig {
-        match package_manager {
-            Some(_) => PackageManagerConfig {
-                path: package_manager.clone().unwrap().path.unwrap_or("".into()),
-                publish: package_manager.clone().unwrap().publish.unwrap_or(false),
-                active: true,
-            },
-            None => PackageManagerConfig {
-                path: "".into(),
-                publish: false,
-                active: false,
-            },
-        }
+        paths
     }
 
-    fn pm_paths(&self, package_manager_config: &PackageManagerConfig) -> Vec<String> {
-        match &package_manager_config.active {
-            true => {
-                let mut paths: Vec<String> = vec![];
-                for file in self.files() {
-                    let path: Vec<&str> = vec![&package_manager_config.path, &file];
-                    paths.push(to_path_str(path));
-                }
-                paths
+    /// Computes the manifest changes needed to set `version` in every package manager file.
+    pub fn version_updates(&self, version: &str) -> Result<Vec<FileUpdate>, RellrError> {
+        let mut updates: Vec<FileUpdate> = vec![];
+        if let Some(package_managers) = &self.package_managers {
+            for package_manager in package_managers.adapters() {
+                updates.append(&mut package_manager.version_updates(&self.name, version)?);
             }
-            false => vec![],
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-struct PackageManagerConfig {
-    pub path: String,
-    pub publish: bool,
-    pub active: bool,
-}
-
-struct Cargo {
-    pub config: PackageManagerConfig,
-}
-
-impl PackageManagerTrait for Cargo {
-    fn new(project_config: &ProjectConfig) -> Self {
-        let package_manager = project_config.package_managers.clone().unwrap().cargo;
-        Self {
-            config: Self::pm_config(&package_manager),
-        }
-    }
-
-    fn files(&self) -> Vec<String> {
-        vec!["Cargo.toml".into(), "Cargo.lock".into()]
-    }
-
-    fn paths(&self) -> Vec<String> {
-        self.pm_paths(&self.config)
-    }
-
-    fn publish(&self) -> Result<(), Box<dyn Error>> {
-        if !&self.config.publish {
-            return Ok(());
         }
-
-        Ok(())
+        Ok(updates)
     }
-}
 
-struct Npm {
-    pub config: PackageManagerConfig,
-}
-
-impl PackageManagerTrait for Npm {
-    fn new(project_config: &ProjectConfig) -> Self {
-        let package_manager = project_config.package_managers.clone().unwrap().npm;
-        Self {
-            config: Self::pm_config(&package_manager),
+    pub fn publish_commands(&self, version: &str) -> Result<Vec<PublishCommand>, RellrError> {
+        let mut commands: Vec<PublishCommand> = vec![];
+        if let Some(package_managers) = &self.package_managers {
+            for package_manager in package_managers.adapters() {
+                commands.append(&mut package_manager.publish_commands(version)?);
+            }
         }
+        Ok(commands)
     }
 
-    fn files(&self) -> Vec<String> {
-        vec!["package.json".into()]
-    }
-
-    fn paths(&self) -> Vec<String> {
-        self.pm_paths(&self.config)
-    }
-
-    fn publish(&self) -> Result<(), Box<dyn Error>> {
-        if !&self.config.publish {
-            return Ok(());
+    pub fn publish(&self, version: &str) -> Result<Vec<PublishOutcome>, RellrError> {
+        let mut outcomes: Vec<PublishOutcome> = vec![];
+        if let Some(package_managers) = &self.package_managers {
+            for package_manager in package_managers.adapters() {
+                outcomes.append(&mut package_manager.publish(version)?);
+            }
         }
 
-        let project_folder = to_abs_path(&self.config.path);
-        let npm = Path::new(r"C:\Program Files\nodejs");
-        assert!(env::set_current_dir(&npm).is_ok());
-
-        #[cfg(windows)]
-        pub const NPM: &'static str = "npm.cmd";
-        #[cfg(not(windows))]
-        pub const NPM: &'static str = "npm";
-
-        env::set_current_dir(&project_folder)?;
-        Msg::new("").info();
-        let _ = Command::new(NPM).arg("publish").spawn()?.wait();
-
-        Ok(())
+        Ok(outcomes)
     }
 }
@@ -0,0 +1,65 @@
+//! A temporary git repository used as the current directory of a unit test.
+
+use std::env;
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The current directory is shared by the whole process, so the tests that change it run one at a time.
+static CURRENT_DIR: Mutex<()> = Mutex::new(());
+
+pub struct TestRepo {
+    pub root: PathBuf,
+    previous: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestRepo {
+    /// Creates an empty repository on `main` and makes it the current directory until the repository is dropped.
+    pub fn new(name: &str) -> Self {
+        let lock = CURRENT_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+        let root = env::temp_dir().join(format!("rellr-unit-{}-{}", name, std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(&root).unwrap();
+        let previous = env::current_dir().unwrap();
+        env::set_current_dir(&root).unwrap();
+
+        let repo = Self { root, previous, _lock: lock };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "rellr"]);
+        repo.git(&["config", "user.email", "rellr@example.com"]);
+        repo
+    }
+
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.root.join(path);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).unwrap();
+        }
+        write(path, contents).unwrap();
+    }
+
+    pub fn read(&self, path: impl AsRef<Path>) -> String {
+        read_to_string(self.root.join(path)).unwrap()
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git").args(args).current_dir(&self.root).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Commits all the changes of the working directory.
+    pub fn commit(&self, message: &str) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = env::set_current_dir(&self.previous);
+        let _ = remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,131 @@
+use super::{UpdateType, VersionSchemeTrait};
+use crate::libs::error::RellrError;
+use crate::libs::msg;
+use chrono::{Datelike, NaiveDate};
+
+/// Calendar versioning: the date parts of the format are taken from the current date and the
+/// `MICRO` (or `PATCH`) counter is incremented within a period and reset when the period changes.
+///
+/// Supported date parts: `YYYY`, `YY`, `0Y`, `MM`, `0M`, `WW`, `0W`, `DD` and `0D`.
+pub struct CalVer {
+    pub format: String,
+    pub today: NaiveDate,
+}
+
+impl CalVer {
+    pub fn new(format: &str, today: NaiveDate) -> Self {
+        Self {
+            format: format.into(),
+            today,
+        }
+    }
+
+    /// Value of a date part for the current date, formatted as in a version.
+    fn date_part(&self, token: &str) -> Result<String, RellrError> {
+        // Weeks belong to the ISO year, so the last days of December may be in week 1 of the next year.
+        let year = match self.format.contains("WW") || self.format.contains("0W") {
+            true => self.today.iso_week().year(),
+            false => self.today.year(),
+        };
+        let part = match token {
+            "YYYY" => year.to_string(),
+            "YY" => (year - 2000).to_string(),
+            "0Y" => format!("{:02}", year - 2000),
+            "MM" => self.today.month().to_string(),
+            "0M" => format!("{:02}", self.today.month()),
+            "WW" => self.today.iso_week().week().to_string(),
+            "0W" => format!("{:02}", self.today.iso_week().week()),
+            "DD" => self.today.day().to_string(),
+            "0D" => format!("{:02}", self.today.day()),
+            _ => return Err(RellrError::Config(format!("{} `{}` in `{}`", msg::INVALID_CALVER_TOKEN, token, self.format))),
+        };
+        Ok(part)
+    }
+
+    fn is_counter(token: &str) -> bool {
+        matches!(token, "MICRO" | "PATCH")
+    }
+}
+
+impl VersionSchemeTrait for CalVer {
+    fn bump(&self, current: &str, update_type: &UpdateType, channel: Option<&str>) -> Result<String, RellrError> {
+        if matches!(update_type, UpdateType::Prerelease | UpdateType::Promote) || channel.is_some() {
+            return Err(RellrError::Version(msg::CALVER_PRERELEASE.into()));
+        }
+
+        let invalid = || RellrError::Version(format!("{} `{}`: expected the format `{}`", msg::INVALID_VERSION, current, self.format));
+        let tokens: Vec<&str> = self.format.split('.').collect();
+        let parts: Vec<&str> = current.split('.').collect();
+        if tokens.len() != parts.len() {
+            return Err(invalid());
+        }
+
+        let mut next: Vec<String> = vec![];
+        let mut current_period: Vec<u64> = vec![];
+        let mut next_period: Vec<u64> = vec![];
+        let mut counter: Option<(usize, u64)> = None;
+        for (index, (token, part)) in tokens.iter().zip(&parts).enumerate() {
+            let value: u64 = part.parse().map_err(|_| invalid())?;
+            if Self::is_counter(token) {
+                counter = Some((index, value));
+                next.push(String::new());
+                continue;
+            }
+            let date_part = self.date_part(token)?;
+            current_period.push(value);
+            next_period.push(date_part.parse().map_err(|_| invalid())?);
+            next.push(date_part);
+        }
+
+        let same_period = next_period == current_period;
+        if let Some((index, value)) = counter {
+            next[index] = if same_period { value + 1 } else { 0 }.to_string();
+        }
+        if next_period < current_period || (same_period && counter.is_none()) {
+            return Err(RellrError::Version(format!("{} {} -> {}", msg::VERSION_NOT_INCREASED, current, next.join("."))));
+        }
+        Ok(next.join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DEFAULT_CALVER_FORMAT;
+    use super::*;
+
+    fn bump(format: &str, today: (i32, u32, u32), current: &str) -> Result<String, RellrError> {
+        let today = NaiveDate::from_ymd_opt(today.0, today.1, today.2).unwrap();
+        CalVer::new(format, today).bump(current, &UpdateType::Patch, None)
+    }
+
+    #[test]
+    fn bump_increments_the_counter_within_the_period() {
+        assert_eq!(bump("YYYY.MM.MICRO", (2026, 10, 18), "2026.10.3").unwrap(), "2026.10.4");
+        assert_eq!(bump("YYYY.0M.0D.PATCH", (2026, 3, 7), "2026.03.07.0").unwrap(), "2026.03.07.1");
+    }
+
+    #[test]
+    fn bump_resets_the_counter_in_a_new_period() {
+        assert_eq!(bump("YYYY.MM.MICRO", (2026, 10, 18), "2026.9.5").unwrap(), "2026.10.0");
+        assert_eq!(bump("YYYY.MM.MICRO", (2026, 1, 2), "2025.12.7").unwrap(), "2026.1.0");
+        assert_eq!(bump("0Y.0M.MICRO", (2026, 1, 5), "25.12.4").unwrap(), "26.01.0");
+        // 2024-12-30 is in week 1 of 2025.
+        assert_eq!(bump("YYYY.0W.MICRO", (2024, 12, 30), "2024.52.3").unwrap(), "2025.01.0");
+    }
+
+    #[test]
+    fn bump_refuses_versions_that_do_not_increase() {
+        assert!(matches!(bump("YYYY.MM.MICRO", (2026, 10, 18), "2027.1.0"), Err(RellrError::Version(_))));
+        assert!(matches!(bump("YYYY.0M.0D", (2026, 10, 18), "2026.10.18"), Err(RellrError::Version(_))));
+        assert_eq!(bump("YYYY.0M.0D", (2026, 10, 18), "2026.10.17").unwrap(), "2026.10.18");
+    }
+
+    #[test]
+    fn bump_rejects_invalid_versions_and_prereleases() {
+        assert!(matches!(bump("YYYY.MM.MICRO", (2026, 10, 18), "2026.10"), Err(RellrError::Version(_))));
+        assert!(matches!(bump("YYYY.QQ.MICRO", (2026, 10, 18), "2026.4.0"), Err(RellrError::Config(_))));
+        let calver = CalVer::new(DEFAULT_CALVER_FORMAT, NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+        assert!(matches!(calver.bump("2026.10.0", &UpdateType::Prerelease, None), Err(RellrError::Version(_))));
+        assert!(matches!(calver.bump("2026.10.0", &UpdateType::Patch, Some("beta")), Err(RellrError::Version(_))));
+    }
+}
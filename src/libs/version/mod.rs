@@ -0,0 +1,200 @@
+mod calendar;
+mod semantic;
+
+pub use calendar::CalVer;
+pub use semantic::SemVer;
+
+use super::error::RellrError;
+use super::msg;
+use chrono::Local;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_CALVER_FORMAT: &str = "YYYY.MM.MICRO";
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UpdateType {
+    Patch,
+    Minor,
+    Major,
+    /// Inferred from the conventional commits since the last release tag
+    Auto,
+    /// Next pre-release of the channel, e.g. `1.3.0-beta.2` -> `1.3.0-beta.3`
+    Prerelease,
+    /// Turns a pre-release into its release, e.g. `1.3.0-beta.3` -> `1.3.0`
+    Promote,
+}
+
+/// How versions are numbered, selected by the `scheme` key of the configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum VersionScheme {
+    #[default]
+    SemVer,
+    /// Calendar versioning, e.g. `YYYY.MM.MICRO` or `YY.0W.PATCH`.
+    CalVer {
+        #[serde(default = "VersionScheme::default_calver_format")]
+        format: String,
+    },
+}
+
+impl VersionScheme {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn adapter(&self) -> Box<dyn VersionSchemeTrait> {
+        match self {
+            VersionScheme::SemVer => Box::new(SemVer::new()),
+            VersionScheme::CalVer { format } => Box::new(CalVer::new(format, Local::now().date_naive())),
+        }
+    }
+
+    fn default_calver_format() -> String {
+        DEFAULT_CALVER_FORMAT.into()
+    }
+}
+
+pub trait VersionSchemeTrait {
+    /// Computes the version following `current`.
+    fn bump(&self, current: &str, update_type: &UpdateType, channel: Option<&str>) -> Result<String, RellrError>;
+
+    /// Whether breaking changes are released as minor updates, like in the 0.x series of SemVer.
+    fn is_initial_development(&self, _current: &str) -> bool {
+        false
+    }
+}
+
+/// A commit that determined the update type inferred by [`BumpConfig::infer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BumpReason {
+    pub commit_id: String,
+    pub summary: String,
+}
+
+/// The `bump` section of the configuration: rules of the `auto` update type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BumpConfig {
+    /// Commit types that bump the major version, in addition to breaking changes.
+    #[serde(default)]
+    pub major: Vec<String>,
+    /// Commit types that bump the minor version; all other commits bump the patch version.
+    #[serde(default = "BumpConfig::default_minor")]
+    pub minor: Vec<String>,
+    /// While the major version is 0, breaking changes only bump the minor version.
+    #[serde(default)]
+    pub initial_development: bool,
+}
+
+impl Default for BumpConfig {
+    fn default() -> Self {
+        Self {
+            major: vec![],
+            minor: Self::default_minor(),
+            initial_development: false,
+        }
+    }
+}
+
+impl BumpConfig {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Picks the largest update required by the commits, given as `(id, message)` pairs,
+    /// and the commits that required it.
+    /// `initial_development` tells whether the current version is in the 0.x series.
+    pub fn infer(&self, initial_development: bool, commits: &[(String, String)]) -> Result<(UpdateType, Vec<BumpReason>), RellrError> {
+        if commits.is_empty() {
+            return Err(RellrError::Version(msg::VERSION_NO_COMMITS.into()));
+        }
+
+        let mut update_type = UpdateType::Patch;
+        let mut reasons: Vec<BumpReason> = vec![];
+        for (id, message) in commits {
+            let commit_update_type = self.update_type(initial_development, message);
+            if commit_update_type > update_type {
+                update_type = commit_update_type;
+                reasons.clear();
+            }
+            if commit_update_type == update_type {
+                reasons.push(BumpReason {
+                    commit_id: id.chars().take(7).collect(),
+                    summary: message.lines().next().unwrap_or_default().to_string(),
+                });
+            }
+        }
+        Ok((update_type, reasons))
+    }
+
+    fn update_type(&self, initial_development: bool, message: &str) -> UpdateType {
+        let Ok(commit) = git_conventional::Commit::parse(message) else {
+            return UpdateType::Patch;
+        };
+        let commit_type = commit.type_().as_str().to_lowercase();
+        if commit.breaking() {
+            return match self.initial_development && initial_development {
+                true => UpdateType::Minor,
+                false => UpdateType::Major,
+            };
+        }
+        if self.major.contains(&commit_type) {
+            UpdateType::Major
+        } else if self.minor.contains(&commit_type) {
+            UpdateType::Minor
+        } else {
+            UpdateType::Patch
+        }
+    }
+
+    fn default_minor() -> Vec<String> {
+        vec!["feat".into()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commits(messages: &[&str]) -> Vec<(String, String)> {
+        messages.iter().enumerate().map(|(index, message)| (format!("{:040}", index), message.to_string())).collect()
+    }
+
+    #[test]
+    fn infer_picks_the_largest_update() {
+        let (update_type, reasons) = BumpConfig::default().infer(false, &commits(&["fix: a", "feat: b", "docs: c", "feat(cli): d"])).unwrap();
+        assert_eq!(update_type, UpdateType::Minor);
+        assert_eq!(reasons.iter().map(|reason| reason.summary.as_str()).collect::<Vec<_>>(), ["feat: b", "feat(cli): d"]);
+        assert_eq!(reasons[0].commit_id, "0000000");
+
+        let (update_type, _) = BumpConfig::default().infer(false, &commits(&["chore: a", "not conventional"])).unwrap();
+        assert_eq!(update_type, UpdateType::Patch);
+    }
+
+    #[test]
+    fn infer_breaking_changes() {
+        let messages = commits(&["feat: a", "fix!: b", "refactor: c\n\nBREAKING CHANGE: d"]);
+        let (update_type, reasons) = BumpConfig::default().infer(false, &messages).unwrap();
+        assert_eq!(update_type, UpdateType::Major);
+        assert_eq!(reasons.len(), 2);
+
+        let config = BumpConfig {
+            initial_development: true,
+            ..BumpConfig::default()
+        };
+        assert_eq!(config.infer(true, &messages).unwrap().0, UpdateType::Minor);
+        assert_eq!(config.infer(false, &messages).unwrap().0, UpdateType::Major);
+    }
+
+    #[test]
+    fn infer_with_configured_types() {
+        let config = BumpConfig {
+            major: vec!["epic".into()],
+            minor: vec!["feat".into(), "perf".into()],
+            initial_development: false,
+        };
+        assert_eq!(config.infer(false, &commits(&["perf: a"])).unwrap().0, UpdateType::Minor);
+        assert_eq!(config.infer(false, &commits(&["Epic: a"])).unwrap().0, UpdateType::Major);
+        assert!(matches!(config.infer(false, &[]), Err(RellrError::Version(_))));
+    }
+}
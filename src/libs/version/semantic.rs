@@ -0,0 +1,168 @@
+use super::{UpdateType, VersionSchemeTrait};
+use crate::libs::error::RellrError;
+use crate::libs::msg;
+use semver::{Prerelease, Version};
+
+pub const DEFAULT_CHANNEL: &str = "alpha";
+
+/// Semantic versioning 2.0, with pre-release channels such as `1.3.0-beta.2`.
+#[derive(Debug, Clone, Default)]
+pub struct SemVer;
+
+impl SemVer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse(version: &str) -> Result<Version, RellrError> {
+        Version::parse(version).map_err(|e| RellrError::Version(format!("{} `{}`: {}", msg::INVALID_VERSION, version, e)))
+    }
+}
+
+impl VersionSchemeTrait for SemVer {
+    /// With a channel, patch, minor and major bumps start a pre-release of the bumped version,
+    /// e.g. `1.2.0` -> `1.3.0-beta.0` for a minor bump in the `beta` channel. A pre-release whose version
+    /// already has the level of the bump is continued instead: `1.3.0-beta.2` -> `1.3.0-beta.3` for a minor bump,
+    /// but `1.3.0-beta.2` -> `1.3.1-beta.0` for a patch bump.
+    /// Build metadata is not carried over.
+    fn bump(&self, current: &str, update_type: &UpdateType, channel: Option<&str>) -> Result<String, RellrError> {
+        let current = Self::parse(current)?;
+        let is_prerelease = !current.pre.is_empty();
+        let (current_channel, number) = split_prerelease(&current.pre);
+        let mut next = Version::new(current.major, current.minor, current.patch);
+
+        match update_type {
+            UpdateType::Patch | UpdateType::Minor | UpdateType::Major => {
+                // Without a channel, a pre-release of the bumped version is released by the bump itself, like `npm version`.
+                let keeps_version = is_prerelease
+                    && match channel {
+                        Some(_) => level(&current) == *update_type,
+                        None => level(&current) >= *update_type,
+                    };
+                if !keeps_version {
+                    increment(&mut next, update_type);
+                }
+                if let Some(channel) = channel {
+                    next.pre = match keeps_version && channel == current_channel {
+                        true => prerelease(channel, number + 1)?,
+                        false => prerelease(channel, 0)?,
+                    };
+                }
+            }
+            UpdateType::Prerelease => {
+                let channel = channel.or(is_prerelease.then_some(current_channel)).unwrap_or(DEFAULT_CHANNEL);
+                next.pre = match is_prerelease && channel == current_channel {
+                    true => prerelease(channel, number + 1)?,
+                    false => prerelease(channel, 0)?,
+                };
+                if !is_prerelease {
+                    next.patch += 1;
+                }
+            }
+            UpdateType::Promote => {
+                if !is_prerelease {
+                    return Err(RellrError::Version(format!("{} {}", msg::VERSION_NOT_A_PRERELEASE, current)));
+                }
+            }
+            UpdateType::Auto => return Err(RellrError::Version(msg::VERSION_AUTO_NOT_RESOLVED.into())),
+        }
+
+        if next <= current {
+            return Err(RellrError::Version(format!("{} {} -> {}", msg::VERSION_NOT_INCREASED, current, next)));
+        }
+        Ok(next.to_string())
+    }
+
+    fn is_initial_development(&self, current: &str) -> bool {
+        Self::parse(current).is_ok_and(|version| version.major == 0)
+    }
+}
+
+/// Level of the release that `version` is: `major` for `2.0.0`, `minor` for `1.3.0` and `patch` for `1.3.1`.
+fn level(version: &Version) -> UpdateType {
+    match (version.minor, version.patch) {
+        (0, 0) => UpdateType::Major,
+        (_, 0) => UpdateType::Minor,
+        _ => UpdateType::Patch,
+    }
+}
+
+/// Bumps the part of `version` given by `update_type`, resetting the lower parts.
+fn increment(version: &mut Version, update_type: &UpdateType) {
+    match update_type {
+        UpdateType::Major => {
+            version.major += 1;
+            version.minor = 0;
+            version.patch = 0;
+        }
+        UpdateType::Minor => {
+            version.minor += 1;
+            version.patch = 0;
+        }
+        _ => version.patch += 1,
+    }
+}
+
+/// Splits `beta.2` into the channel and the pre-release number; the number is 0 when there is none.
+fn split_prerelease(pre: &Prerelease) -> (&str, u64) {
+    if let Some((channel, number)) = pre.as_str().rsplit_once('.') {
+        if let Ok(number) = number.parse() {
+            return (channel, number);
+        }
+    }
+    (pre.as_str(), 0)
+}
+
+fn prerelease(channel: &str, number: u64) -> Result<Prerelease, RellrError> {
+    Prerelease::new(&format!("{}.{}", channel, number)).map_err(|e| RellrError::Version(format!("{} `{}`: {}", msg::INVALID_CHANNEL, channel, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump(current: &str, update_type: UpdateType, channel: Option<&str>) -> Result<String, RellrError> {
+        SemVer::new().bump(current, &update_type, channel)
+    }
+
+    #[test]
+    fn bump_starts_a_channel() {
+        assert_eq!(bump("1.2.0", UpdateType::Minor, Some("beta")).unwrap(), "1.3.0-beta.0");
+        assert_eq!(bump("1.2.3", UpdateType::Prerelease, None).unwrap(), "1.2.4-alpha.0");
+        assert_eq!(bump("1.2.3+build.5", UpdateType::Patch, None).unwrap(), "1.2.4");
+    }
+
+    #[test]
+    fn bump_prerelease_continues_or_switches_channel() {
+        assert_eq!(bump("1.3.0-beta.1", UpdateType::Prerelease, None).unwrap(), "1.3.0-beta.2");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Prerelease, Some("rc")).unwrap(), "1.3.0-rc.0");
+        assert!(matches!(bump("1.3.0-rc.1", UpdateType::Prerelease, Some("beta")), Err(RellrError::Version(_))));
+        assert!(matches!(bump("1.3.0", UpdateType::Prerelease, Some("be ta")), Err(RellrError::Version(_))));
+    }
+
+    #[test]
+    fn bump_in_a_channel_continues_a_prerelease_of_the_same_level() {
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Minor, Some("beta")).unwrap(), "1.3.0-beta.3");
+        assert_eq!(bump("1.3.1-beta.0", UpdateType::Patch, Some("beta")).unwrap(), "1.3.1-beta.1");
+        assert_eq!(bump("2.0.0-beta.1", UpdateType::Major, Some("beta")).unwrap(), "2.0.0-beta.2");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Minor, Some("rc")).unwrap(), "1.3.0-rc.0");
+        assert!(matches!(bump("1.3.0-rc.1", UpdateType::Minor, Some("beta")), Err(RellrError::Version(_))));
+    }
+
+    #[test]
+    fn bump_in_a_channel_starts_a_prerelease_of_another_level() {
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Patch, Some("beta")).unwrap(), "1.3.1-beta.0");
+        assert_eq!(bump("1.3.1-beta.2", UpdateType::Minor, Some("beta")).unwrap(), "1.4.0-beta.0");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Major, Some("beta")).unwrap(), "2.0.0-beta.0");
+    }
+
+    #[test]
+    fn bump_releases_the_prerelease() {
+        assert_eq!(bump("1.3.0-rc.1", UpdateType::Promote, None).unwrap(), "1.3.0");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Patch, None).unwrap(), "1.3.0");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Minor, None).unwrap(), "1.3.0");
+        assert_eq!(bump("1.3.0-beta.2", UpdateType::Major, None).unwrap(), "2.0.0");
+        assert_eq!(bump("2.0.0-beta.2", UpdateType::Major, None).unwrap(), "2.0.0");
+        assert!(matches!(bump("1.3.0", UpdateType::Promote, None), Err(RellrError::Version(_))));
+    }
+}
@@ -1,8 +1,8 @@
 mod commands;
 use clap::{Parser, Subcommand};
-use commands::{feat, fix, init, next, release};
-use std::error::Error;
-mod libs;
+use commands::{changelog, feat, fix, init, next, release};
+use rellr::libs::msg::Msg;
+use std::process;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -10,6 +10,8 @@ mod libs;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    global: commands::GlobalArgs,
 }
 
 #[derive(Debug, Subcommand)]
@@ -24,16 +26,24 @@ enum Commands {
     Fix(fix::FixArgs),
     #[command(about = "Release")]
     Release(release::ReleaseArgs),
+    #[command(about = "Release notes")]
+    Changelog(changelog::ChangelogArgs),
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Init(args) => init::cmd(args),
-        Commands::Next(args) => next::cmd(args),
-        Commands::Feat(args) => feat::cmd(args),
-        Commands::Fix(args) => fix::cmd(args),
-        Commands::Release(args) => release::cmd(args),
+    let result = match cli.command {
+        Commands::Init(args) => init::cmd(args, &cli.global),
+        Commands::Next(args) => next::cmd(args, &cli.global),
+        Commands::Feat(args) => feat::cmd(args, &cli.global),
+        Commands::Fix(args) => fix::cmd(args, &cli.global),
+        Commands::Release(args) => release::cmd(args, &cli.global),
+        Commands::Changelog(args) => changelog::cmd(args, &cli.global),
+    };
+
+    if let Err(err) = result {
+        Msg::new(&err.to_string()).error();
+        process::exit(err.exit_code());
     }
 }
@@ -0,0 +1,486 @@
+use crate::libs::changelog::Changelog;
+use crate::libs::error::RellrError;
+use crate::libs::git::{BranchType, CommitOutcome, Git, MergeOutcome};
+use crate::libs::helpers::check_files_existence;
+use crate::libs::journal::{Journal, Step};
+use crate::libs::msg;
+use crate::libs::package_managers::{FileUpdate, PublishOutcome};
+use crate::libs::plan::{Action, Plan};
+use crate::libs::project_config::ProjectConfig;
+use crate::libs::version::{BumpReason, UpdateType};
+use git2::Repository;
+use std::fs::write;
+
+/// Version change planned by [`Release::plan_bump`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BumpPlan {
+    pub current: String,
+    pub prev: Option<String>,
+    pub next: String,
+    /// The applied update type; for `auto` the one inferred from the commits.
+    pub update_type: UpdateType,
+    /// Commits that determined the inferred update type, empty unless `auto` was requested.
+    pub reasons: Vec<BumpReason>,
+}
+
+/// The changelog files written by [`Release::changelog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogOutput {
+    pub path: String,
+    /// Changelogs of the packages in `changelog.packages`.
+    pub package_paths: Vec<String>,
+}
+
+/// Release workflow. Every step can be called on its own, in order:
+/// [`bump`](Release::bump), [`changelog`](Release::changelog), [`merge`](Release::merge),
+/// [`update_versions`](Release::update_versions), [`commit`](Release::commit) and [`publish`](Release::publish).
+///
+/// In dry-run mode the steps record their actions in a [`Plan`] instead of changing files, refs or the index.
+///
+/// Steps run inside a [`transaction`](Release::transaction) are recorded in a [`Journal`] and rolled back
+/// together when one of them fails.
+pub struct Release {
+    pub project_config: ProjectConfig,
+    version: Option<String>,
+    plan: Option<Plan>,
+    journal: Option<Journal>,
+    stash: bool,
+    channel: Option<String>,
+}
+
+impl Release {
+    pub fn new(project_config: ProjectConfig) -> Self {
+        let version = project_config.next.clone();
+        Self {
+            project_config,
+            version,
+            plan: None,
+            journal: None,
+            stash: false,
+            channel: None,
+        }
+    }
+
+    /// Loads the project configuration from the current directory.
+    pub fn load() -> Result<Self, RellrError> {
+        Ok(Self::new(ProjectConfig::get()?))
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.plan = dry_run.then(Plan::default);
+        self
+    }
+
+    /// Loads the release interrupted by a crash or a failed rollback, to finish its pending steps.
+    pub fn resume() -> Result<Self, RellrError> {
+        let mut release = Self::load()?;
+        let journal = Journal::load(&Repository::open(".")?)?.ok_or(RellrError::Git(msg::RELEASE_NOT_IN_PROGRESS.into()))?;
+        release.version = Some(journal.version.clone());
+        release.journal = Some(journal);
+        Ok(release)
+    }
+
+    /// Stashes local changes that would be overwritten when switching branches, instead of failing.
+    pub fn stash(mut self, stash: bool) -> Self {
+        self.stash = stash;
+        self
+    }
+
+    /// Pre-release channel used by [`bump`](Release::bump), e.g. `beta`.
+    pub fn channel(mut self, channel: Option<String>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Rolls back the interrupted release and returns its version.
+    pub fn abort(dry_run: bool) -> Result<(String, Option<Plan>), RellrError> {
+        let repo = Repository::open(".")?;
+        let journal = Journal::load(&repo)?.ok_or(RellrError::Git(msg::RELEASE_NOT_IN_PROGRESS.into()))?;
+        if dry_run {
+            let mut plan = Plan::default();
+            plan.push(Action::Rollback {
+                version: journal.version.clone(),
+            });
+            return Ok((journal.version, Some(plan)));
+        }
+
+        journal.rollback(&repo)?;
+        Ok((journal.version, None))
+    }
+
+    /// Runs the steps in `f` as a single transaction: every ref and file they change is journaled first,
+    /// and all of it is restored when a step fails. Steps finished by an interrupted run are skipped
+    /// via [`is_pending`](Release::is_pending) after [`resume`](Release::resume).
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, RellrError>) -> Result<T, RellrError> {
+        if self.plan.is_some() {
+            return f(self);
+        }
+
+        self.begin()?;
+        match f(self) {
+            Ok(value) => {
+                self.journal = None;
+                Journal::finish(&Git::open(&self.project_config)?.repo)?;
+                Ok(value)
+            }
+            // Conflicts are left for the user to resolve before `--continue` or `--abort`.
+            Err(err @ RellrError::MergeConflicts(_)) => Err(err),
+            Err(err) => match self.rollback() {
+                Ok(()) => Err(err),
+                Err(rollback_err) => Err(RellrError::Git(format!("{}\n{}\n{}", err, msg::RELEASE_IN_PROGRESS, rollback_err))),
+            },
+        }
+    }
+
+    /// Whether a release in the current directory was interrupted and not yet continued or aborted.
+    pub fn is_interrupted() -> Result<bool, RellrError> {
+        match Repository::open(".") {
+            Ok(repo) => Ok(Journal::load(&repo)?.is_some()),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Starts the journal of a transaction; fails when an interrupted release has not been resolved yet.
+    pub fn begin(&mut self) -> Result<(), RellrError> {
+        if self.plan.is_none() && self.journal.is_none() {
+            let git = Git::new(&self.project_config)?;
+            self.journal = Some(Journal::begin(&git.repo, &self.version()?)?);
+        }
+        Ok(())
+    }
+
+    /// Whether a transaction has been started and not yet finished or rolled back.
+    pub fn in_progress(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Whether the step still has to run; always true outside a resumed transaction.
+    pub fn is_pending(&self, step: Step) -> bool {
+        !self.journal.as_ref().is_some_and(|journal| journal.is_completed(step))
+    }
+
+    /// Actions recorded so far in dry-run mode.
+    pub fn plan(&self) -> Option<&Plan> {
+        self.plan.as_ref()
+    }
+
+    /// The version being released.
+    pub fn version(&self) -> Result<String, RellrError> {
+        self.version.clone().ok_or(RellrError::Version(msg::RELEASE_VERSION_NOT_SET.into()))
+    }
+
+    /// Computes the next version without changing anything.
+    pub fn plan_bump(&self, update_type: &UpdateType) -> Result<BumpPlan, RellrError> {
+        let (update_type, reasons) = match update_type {
+            UpdateType::Auto => {
+                let commits = Git::open(&self.project_config)?.commits_since_last_tag()?;
+                let initial_development = self.project_config.scheme.adapter().is_initial_development(&self.project_config.current);
+                self.project_config.bump.infer(initial_development, &commits)?
+            }
+            update_type => (*update_type, vec![]),
+        };
+        let project_config = self.project_config.clone().up_version(&update_type, self.channel.as_deref())?;
+        Ok(BumpPlan {
+            current: project_config.current.clone(),
+            prev: project_config.prev.clone(),
+            next: project_config.next_version()?,
+            update_type,
+            reasons,
+        })
+    }
+
+    /// Sets the next version, saves the configuration and switches to the release branch.
+    pub fn bump(&mut self, update_type: &UpdateType) -> Result<BumpPlan, RellrError> {
+        let plan = self.plan_bump(update_type)?;
+        self.project_config.up_version(&plan.update_type, self.channel.as_deref())?;
+        self.save_config()?;
+        self.version = Some(plan.next.clone());
+
+        if self.plan.is_none() {
+            Git::new(&self.project_config)?.with_stash(self.stash).add_or_rename_next_branch()?.checkout_next()?;
+            return Ok(plan);
+        }
+
+        let branch_type = &self.project_config.branch_type;
+        let next_branch = branch_type.branch_name(&plan.next);
+        let prev_branch = plan.prev.as_ref().map(|prev| branch_type.branch_name(prev));
+        let main_branch = self.project_config.main_branch.clone();
+        let git = self.open_git()?;
+        let has_branch = |name: &str| git.as_ref().is_some_and(|git| git.has_branch(name));
+
+        let mut actions: Vec<Action> = vec![];
+        match prev_branch {
+            Some(prev_branch) if has_branch(&prev_branch) => actions.push(Action::RenameBranch {
+                from: prev_branch,
+                to: next_branch.clone(),
+            }),
+            _ if !has_branch(&next_branch) => actions.push(Action::CreateBranch {
+                name: next_branch.clone(),
+                from: main_branch,
+            }),
+            _ => {}
+        }
+        actions.push(Action::Checkout { branch: next_branch });
+        self.record(actions);
+        Ok(plan)
+    }
+
+    /// Creates a feature or hotfix branch from the main branch and switches to it.
+    pub fn start_branch(&mut self, branch_type: BranchType, name: &str) -> Result<String, RellrError> {
+        let branch_name = branch_type.branch_name(name);
+        self.project_config.branch_type = branch_type;
+
+        if self.plan.is_none() {
+            Git::new(&self.project_config)?.with_stash(self.stash).add_branch_and_checkout(name)?;
+            return Ok(branch_name);
+        }
+
+        let git = self.open_git()?;
+        let mut actions: Vec<Action> = vec![];
+        if !git.as_ref().is_some_and(|git| git.has_branch(&branch_name)) {
+            actions.push(Action::CreateBranch {
+                name: branch_name.clone(),
+                from: self.project_config.main_branch.clone(),
+            });
+        }
+        actions.push(Action::Checkout { branch: branch_name.clone() });
+        self.record(actions);
+        Ok(branch_name)
+    }
+
+    /// Files that are changed by the release and committed with it.
+    pub fn files(&self) -> Vec<String> {
+        let mut paths = self.project_config.paths();
+        let changelog_options = self.project_config.changelog.clone().unwrap_or_default();
+        paths.push(changelog_options.output_file_name());
+        paths.extend(changelog_options.packages.iter().map(|package| package.output_file_name()));
+        paths.push(self.project_config.config_file_name().into());
+        paths
+    }
+
+    /// Fails when a file of a configured package manager is missing.
+    pub fn check_files(&self) -> Result<(), RellrError> {
+        let paths = self.project_config.paths();
+        let non_existent_files = check_files_existence(paths.iter().map(|s| s.as_str()).collect());
+        if !non_existent_files.is_empty() {
+            return Err(RellrError::PackageManager(format!(
+                "{}\n{}\n{}",
+                msg::RELEASE_FAILED,
+                msg::FILES_ARE_MISSING,
+                &non_existent_files.join("\n")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn changelog(&mut self) -> Result<ChangelogOutput, RellrError> {
+        self.version()?;
+        let mut changelogs = vec![Changelog::open(&self.project_config)?];
+        for package in self.project_config.changelog.clone().unwrap_or_default().packages {
+            changelogs.push(Changelog::open(&self.project_config)?.with_package(Some(&package.name))?);
+        }
+        let paths: Vec<String> = changelogs.iter().map(|changelog| changelog.output_file_name()).collect();
+        match self.plan.is_some() {
+            true => {
+                let mut actions = vec![];
+                for changelog in &changelogs {
+                    actions.push(Action::Changelog {
+                        path: changelog.output_file_name(),
+                        section: changelog.section()?,
+                    });
+                }
+                self.record(actions);
+            }
+            false => {
+                self.journal_files(&paths)?;
+                for changelog in &changelogs {
+                    changelog.build()?;
+                }
+                self.complete(Step::Changelog)?;
+            }
+        }
+        Ok(ChangelogOutput {
+            path: paths[0].clone(),
+            package_paths: paths[1..].to_vec(),
+        })
+    }
+
+    /// Merges the release branch into the main branch and deletes it.
+    /// With `stash`, the files written by the release, i.e. the changelogs and the configuration, are not stashed.
+    pub fn merge(&mut self) -> Result<MergeOutcome, RellrError> {
+        if self.plan.is_none() {
+            let next_branch = self.project_config.branch_type.branch_name(&self.version()?);
+            self.journal_refs(&[self.main_branch_ref(), format!("refs/heads/{}", next_branch)])?;
+            let mut kept: Vec<String> = self.journal.iter().flat_map(|journal| journal.files.iter().map(|file| file.path.clone())).collect();
+            kept.push(self.project_config.config_file_name().into());
+            let outcome = Git::new(&self.project_config)?.with_stash(self.stash).with_kept_paths(kept).merge()?;
+            self.complete(Step::Merge)?;
+            return Ok(outcome);
+        }
+
+        let mut git = Git::open(&self.project_config)?;
+        let outcome = git.merge_analysis()?;
+        let version = self.version()?;
+        let next_branch = self.project_config.branch_type.branch_name(&version);
+        let main_branch = self.project_config.main_branch.clone();
+        let strategy = match outcome {
+            MergeOutcome::FastForward => "fast-forward",
+            MergeOutcome::Normal => "normal",
+            MergeOutcome::UpToDate => "up to date",
+        };
+        self.record(vec![
+            Action::Checkout { branch: main_branch.clone() },
+            Action::Merge {
+                from: next_branch.clone(),
+                into: main_branch,
+                strategy: strategy.into(),
+                message: (outcome == MergeOutcome::Normal).then(|| git.merge_message(&version)),
+            },
+            Action::DeleteBranch { name: next_branch },
+        ]);
+        Ok(outcome)
+    }
+
+    /// Moves `next` to `current`, saves the configuration and writes the new version into the package manager files.
+    pub fn update_versions(&mut self) -> Result<Vec<FileUpdate>, RellrError> {
+        let version = self.version()?;
+        let mut paths = self.project_config.paths();
+        paths.push(self.project_config.config_file_name().into());
+        self.journal_files(&paths)?;
+
+        if self.project_config.next.is_some() {
+            self.project_config.next_to_current()?;
+            self.save_config()?;
+        }
+
+        let updates = self.project_config.version_updates(&version)?;
+        for update in updates.iter().filter(|update| update.is_changed()) {
+            match self.plan.as_mut() {
+                Some(plan) => plan.write_file(&update.path, &update.before, &update.after),
+                None => write(&update.path, &update.after)?,
+            }
+        }
+        self.complete(Step::UpdateVersions)?;
+        Ok(updates)
+    }
+
+    /// Commits the release files and tags the commit.
+    pub fn commit(&mut self) -> Result<CommitOutcome, RellrError> {
+        let version = self.version()?;
+        let files = self.files();
+        if self.plan.is_none() {
+            let mut git = Git::new(&self.project_config)?;
+            let mut refs = vec![self.main_branch_ref(), format!("refs/tags/{}", git.tag_name(&version))];
+            refs.extend(git.package_tag_names(&version).iter().map(|tag| format!("refs/tags/{}", tag)));
+            self.journal_refs(&refs)?;
+            let outcome = git.commit(files.iter().map(|s| s.as_str()).collect(), &version)?;
+            self.complete(Step::Commit)?;
+            return Ok(outcome);
+        }
+
+        let git = Git::open(&self.project_config)?;
+        let outcome = CommitOutcome {
+            commit_id: String::new(),
+            tag: git.tag_name(&version),
+            package_tags: git.package_tag_names(&version),
+        };
+        let mut actions = vec![
+            Action::Commit {
+                message: git.commit_message(&version),
+                files,
+            },
+            Action::Tag { name: outcome.tag.clone() },
+        ];
+        actions.extend(outcome.package_tags.iter().map(|tag| Action::Tag { name: tag.clone() }));
+        self.record(actions);
+        Ok(outcome)
+    }
+
+    pub fn publish(&mut self) -> Result<Vec<PublishOutcome>, RellrError> {
+        let version = self.version()?;
+        if self.plan.is_none() {
+            return self.project_config.publish(&version);
+        }
+
+        let actions = self
+            .project_config
+            .publish_commands(&version)?
+            .into_iter()
+            .map(|command| Action::Publish {
+                command: command.to_string(),
+                cwd: command.cwd.clone(),
+            })
+            .collect();
+        self.record(actions);
+        Ok(vec![])
+    }
+
+    fn rollback(&mut self) -> Result<(), RellrError> {
+        if let Some(journal) = &self.journal {
+            journal.rollback(&Git::open(&self.project_config)?.repo)?;
+            self.journal = None;
+        }
+        Ok(())
+    }
+
+    fn main_branch_ref(&self) -> String {
+        format!("refs/heads/{}", self.project_config.main_branch)
+    }
+
+    /// Journals the targets of the refs before a step changes them.
+    fn journal_refs(&mut self, names: &[String]) -> Result<(), RellrError> {
+        if let Some(journal) = self.journal.as_mut() {
+            let repo = Git::open(&self.project_config)?.repo;
+            for name in names {
+                journal.record_ref(&repo, name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Journals the contents of the files before a step writes them.
+    fn journal_files(&mut self, paths: &[String]) -> Result<(), RellrError> {
+        if let Some(journal) = self.journal.as_mut() {
+            let repo = Git::open(&self.project_config)?.repo;
+            for path in paths {
+                journal.record_file(&repo, path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self, step: Step) -> Result<(), RellrError> {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.complete(&Git::open(&self.project_config)?.repo, step)?;
+        }
+        Ok(())
+    }
+
+    fn save_config(&mut self) -> Result<(), RellrError> {
+        match self.plan.as_mut() {
+            Some(plan) => {
+                let (before, after) = self.project_config.render()?;
+                plan.write_file(self.project_config.config_file_name(), &before, &after);
+                Ok(())
+            }
+            None => self.project_config.save(),
+        }
+    }
+
+    /// Opens the repository for planning; records its initialization when there is none yet.
+    fn open_git(&mut self) -> Result<Option<Git>, RellrError> {
+        if Git::exists() {
+            return Ok(Some(Git::open(&self.project_config)?));
+        }
+        self.record(vec![Action::InitRepository {
+            main_branch: self.project_config.main_branch.clone(),
+        }]);
+        Ok(None)
+    }
+
+    fn record(&mut self, actions: Vec<Action>) {
+        if let Some(plan) = self.plan.as_mut() {
+            plan.actions.extend(actions);
+        }
+    }
+}
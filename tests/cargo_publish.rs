@@ -0,0 +1,72 @@
+//! The order in which `rellr release` publishes the crates of a workspace, with a fake cargo given in `CARGO`:
+//! it records its calls and "publishes" a crate by adding its name to a registry file.
+//! Publishing with the real cargo is tested in `cargo_registry.rs`.
+#![cfg(unix)]
+
+mod common;
+
+use common::Repo;
+use std::fs::{read_to_string, set_permissions, write, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::process::Output;
+
+const FAKE_CARGO: &str = r#"#!/bin/sh
+dir="$(dirname "$0")"
+echo "$*" >> "$dir/calls.log"
+case "$*" in
+  --version) echo "cargo 1.90.0 (fake)"; exit 0;;
+  *--dry-run*) exit 0;;
+esac
+echo "${3:?}" >> "$dir/registry.log"
+"#;
+
+/// A git repository holding a workspace of `a` and `b`, where `b` depends on `a`.
+/// `b` is listed first, so the order has to come from the dependencies.
+struct Workspace {
+    repo: Repo,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let repo = Repo::new(name);
+        repo.write("Cargo.toml", "[workspace]\nmembers = [\"b\", \"a\"]\n");
+        repo.write("a/Cargo.toml", "[package]\nname = \"a\"\nversion = \"0.1.0\"\n");
+        repo.write("b/Cargo.toml", "[package]\nname = \"b\"\nversion = \"0.1.0\"\n\n[dependencies]\na = { path = \"../a\", version = \"0.1.0\" }\n");
+        repo.write("a/src/lib.rs", "");
+        repo.write("b/src/lib.rs", "");
+        repo.write(
+            "rellr.json",
+            "{\n  \"name\": \"demo\",\n  \"current\": \"0.1.0\",\n  \"main_branch\": \"main\",\n  \"package_managers\": [{ \"type\": \"cargo\", \"publish\": true }]\n}\n",
+        );
+        repo.commit("feat: initial commit");
+        let cargo = repo.root.join("cargo.sh");
+        write(&cargo, FAKE_CARGO).unwrap();
+        set_permissions(&cargo, Permissions::from_mode(0o755)).unwrap();
+        Self { repo }
+    }
+
+    /// Runs `rellr next minor`, then `rellr release` with the fake cargo.
+    fn release(&self) -> Output {
+        assert!(self.rellr(&["next", "minor"]).status.success());
+        self.rellr(&["release"])
+    }
+
+    fn rellr(&self, args: &[&str]) -> Output {
+        let cargo = self.repo.root.join("cargo.sh");
+        self.repo.rellr(args, &[("CARGO", cargo.to_str().unwrap())])
+    }
+
+    fn lines(&self, file: &str) -> Vec<String> {
+        read_to_string(self.repo.root.join(file)).unwrap_or_default().lines().map(String::from).collect()
+    }
+}
+
+#[test]
+fn publishes_crates_in_dependency_order() {
+    let workspace = Workspace::new("publish-order");
+    let output = workspace.release();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(workspace.lines("registry.log"), ["a", "b"]);
+    assert_eq!(workspace.lines("calls.log")[1..], ["publish --dry-run -p a -p b", "publish -p a", "publish -p b"]);
+}
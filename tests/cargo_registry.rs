@@ -0,0 +1,149 @@
+//! Publishing a crate through `rellr release` with the real cargo, to an alternative registry
+//! whose git index is a `file://` URL configured in `.cargo/config.toml`.
+#![cfg(unix)]
+
+mod common;
+
+use common::Repo;
+use std::fs::{create_dir_all, read, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const TOKEN_ENV: &str = "LOCAL_REGISTRY_TOKEN";
+
+/// Cargo uploads a crate by writing the request body to the `file://` API URL. The thread adds each
+/// upload to the index, as the server of a registry would, so that cargo sees the published crate.
+struct Registry {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Vec<String>>>,
+}
+
+impl Registry {
+    fn start(root: &Path) -> Self {
+        let index = root.join("index");
+        create_dir_all(&index).unwrap();
+        create_dir_all(root.join("api/api/v1/crates")).unwrap();
+        let config = format!(r#"{{ "dl": "file://{0}/dl/{{crate}}-{{version}}.crate", "api": "file://{0}/api" }}"#, root.display());
+        write(index.join("config.json"), config).unwrap();
+        git(&index, &["init", "-q", "-b", "main"]);
+        git(&index, &["add", "-A"]);
+        git(&index, &["commit", "-q", "-m", "Create the index"]);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let upload = root.join("api/api/v1/crates/new");
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let mut published: Vec<String> = vec![];
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some((name, version)) = read(&upload).ok().and_then(|body| parse_upload(&body)) {
+                        add_to_index(&index, &name, &version);
+                        remove_file(&upload).unwrap();
+                        published.push(format!("{}@{}", name, version));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                published
+            }
+        });
+        Self { stop, thread: Some(thread) }
+    }
+
+    /// Crates uploaded to the registry, as `name@version`.
+    fn stop(mut self) -> Vec<String> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.take().unwrap().join().unwrap()
+    }
+}
+
+/// Name and version of a complete upload: the length and JSON of the metadata, then the length and bytes of the crate.
+fn parse_upload(body: &[u8]) -> Option<(String, String)> {
+    let length = |at: usize| Some(u32::from_le_bytes(body.get(at..at + 4)?.try_into().ok()?) as usize);
+    let metadata_length = length(0)?;
+    let crate_length = length(4 + metadata_length)?;
+    if body.len() != 8 + metadata_length + crate_length {
+        return None;
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&body[4..4 + metadata_length]).ok()?;
+    Some((metadata["name"].as_str()?.to_string(), metadata["vers"].as_str()?.to_string()))
+}
+
+/// Adds a version to the index file of a crate with a one-letter name.
+fn add_to_index(index: &Path, name: &str, version: &str) {
+    let entry = format!(r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"{}","features":{{}},"yanked":false}}"#, name, version, "0".repeat(64));
+    create_dir_all(index.join("1")).unwrap();
+    write(index.join("1").join(name), entry + "\n").unwrap();
+    git(index, &["add", "-A"]);
+    git(index, &["commit", "-q", "-m", &format!("Publish {}@{}", name, version)]);
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git").args(["-c", "user.name=rellr", "-c", "user.email=rellr@example.com"]).args(args).current_dir(dir).output().unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+/// A workspace with the crate `a`, released to the registry `local` with the token of [`TOKEN_ENV`].
+fn workspace(name: &str) -> Repo {
+    let repo = Repo::new(name);
+    let index = repo.root.join("registry/index");
+    repo.write(".cargo/config.toml", &format!("[registries.local]\nindex = \"file://{}\"\n", index.display()));
+    repo.write(".gitignore", "target/\n");
+    repo.write("Cargo.toml", "[workspace]\nmembers = [\"a\"]\n");
+    repo.write("a/Cargo.toml", "[package]\nname = \"a\"\nversion = \"0.1.0\"\ndescription = \"A crate\"\nlicense = \"MIT\"\n");
+    repo.write("a/src/lib.rs", "");
+    repo.write(
+        "rellr.json",
+        &format!(
+            "{{\n  \"name\": \"demo\",\n  \"current\": \"0.1.0\",\n  \"main_branch\": \"main\",\n  \"package_managers\": [{{ \"type\": \"cargo\", \"publish\": true, \"registry\": \"local\", \"token_env\": \"{}\" }}]\n}}\n",
+            TOKEN_ENV
+        ),
+    );
+    repo.commit("feat: initial commit");
+    repo
+}
+
+/// Runs rellr with the real cargo, a fresh cargo home and the registry token.
+fn rellr(repo: &Repo, args: &[&str]) -> Output {
+    let cargo_home: PathBuf = repo.root.join("cargo-home");
+    repo.rellr(args, &[("CARGO", env!("CARGO")), ("CARGO_HOME", cargo_home.to_str().unwrap()), (TOKEN_ENV, "secret")])
+}
+
+#[test]
+fn publishes_to_a_local_registry() {
+    let repo = workspace("cargo-registry");
+    let registry = Registry::start(&repo.root.join("registry"));
+    assert!(rellr(&repo, &["next", "minor"]).status.success());
+
+    let plan = rellr(&repo, &["--dry-run", "release"]);
+    let stdout = String::from_utf8_lossy(&plan.stdout);
+    assert!(plan.status.success(), "{}", stdout);
+    assert!(stdout.contains("publish -p a --registry local"), "{}", stdout);
+
+    // The cargo home is empty, so cargo can only get the token from `CARGO_REGISTRIES_LOCAL_TOKEN`.
+    let output = rellr(&repo, &["release"]);
+    let published = registry.stop();
+    assert!(output.status.success(), "{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert_eq!(published, ["a@0.2.0"]);
+}
+
+#[test]
+fn fails_without_the_registry_token() {
+    let repo = workspace("cargo-registry-token");
+    let registry = Registry::start(&repo.root.join("registry"));
+    assert!(rellr(&repo, &["next", "minor"]).status.success());
+
+    // Without `token_env`, cargo finds no token for the registry and nothing is uploaded.
+    let config = repo.read("rellr.json").replace(&format!(", \"token_env\": \"{}\"", TOKEN_ENV), "");
+    repo.write("rellr.json", &config);
+    repo.commit("chore: publish without a token");
+    let output = rellr(&repo, &["release"]);
+    let published = registry.stop();
+    assert_eq!(output.status.code(), Some(6), "{}", String::from_utf8_lossy(&output.stdout));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no token found for `local`"), "{}", stderr);
+    assert!(published.is_empty());
+}
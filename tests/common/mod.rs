@@ -0,0 +1,68 @@
+//! A temporary git repository in which a test runs `rellr`.
+#![allow(dead_code)]
+
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// A repository on `main` in `rellr-{name}-{pid}/repo` of the temporary directory.
+/// The files of the test that are not part of the repository go next to it, in `root`.
+pub struct Repo {
+    pub root: PathBuf,
+}
+
+impl Repo {
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("rellr-{}-{}", name, std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(root.join("repo")).unwrap();
+        let repo = Self { root };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "rellr"]);
+        repo.git(&["config", "user.email", "rellr@example.com"]);
+        repo
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.root.join("repo")
+    }
+
+    /// Writes a file of the repository, creating its folders.
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.dir().join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        write(path, contents).unwrap();
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        read_to_string(self.dir().join(path)).unwrap()
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git").args(args).current_dir(self.dir()).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Commits all the files of the working directory.
+    pub fn commit(&self, message: &str) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+
+    pub fn rellr(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_rellr"))
+            .args(args)
+            .current_dir(self.dir())
+            .envs(envs.iter().copied())
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,39 @@
+//! `rellr --stash release` with local changes that conflict with the merge into the main branch.
+
+mod common;
+
+use common::Repo;
+
+fn repo(name: &str) -> Repo {
+    let repo = Repo::new(name);
+    repo.write("rellr.json", "{\n  \"name\": \"demo\",\n  \"current\": \"1.0.0\",\n  \"main_branch\": \"main\"\n}\n");
+    repo.write("CHANGELOG.md", "# Changelog\n");
+    repo.write("notes.txt", "one\n");
+    repo.write("todo.txt", "one\n");
+    repo.commit("feat: initial commit");
+    repo
+}
+
+#[test]
+fn stash_keeps_the_changelog_in_the_release() {
+    let repo = repo("release-stash");
+    assert!(repo.rellr(&["next", "minor"], &[]).status.success());
+    repo.write("notes.txt", "two\n");
+    repo.git(&["commit", "-q", "-a", "-m", "fix: update the notes"]);
+    // `notes.txt` differs between the branches, so checking out the main branch needs a stash.
+    repo.write("notes.txt", "local\n");
+    repo.write("todo.txt", "local\n");
+
+    let output = repo.rellr(&["--stash", "release"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let committed = repo.git(&["show", "--name-only", "--format=", "HEAD"]);
+    assert_eq!(committed.lines().collect::<Vec<_>>(), ["CHANGELOG.md", "rellr.json"]);
+    assert!(repo.read("CHANGELOG.md").contains("## 🎉 [1.1.0]"));
+
+    let stashed = repo.git(&["stash", "show", "--name-only", "stash@{0}"]);
+    assert_eq!(stashed.lines().collect::<Vec<_>>(), ["notes.txt"]);
+    assert_eq!(repo.read("notes.txt"), "two\n");
+    assert_eq!(repo.read("todo.txt"), "local\n");
+    assert_eq!(repo.git(&["status", "--short"]), " M todo.txt\n");
+}